@@ -0,0 +1,49 @@
+//! Survey layouts written by earlier versions of the contract.
+
+use near_sdk::json_types::U128;
+use near_sdk::{near, AccountId, NearToken};
+
+use crate::{Survey, SurveyStatus};
+
+/// Survey layout of the first version, always funded with native NEAR.
+#[near(serializers = [borsh])]
+#[derive(Clone)]
+pub struct SurveyV1 {
+    survey_creator: AccountId,
+    participants_limit: u64,
+    reward_amount: NearToken,
+    participants_rewarded: u64,
+    is_canceled: bool,
+}
+
+impl From<SurveyV1> for Survey {
+    fn from(survey: SurveyV1) -> Self {
+        Survey {
+            survey_creator: survey.survey_creator,
+            participants_limit: survey.participants_limit,
+            reward_amount: U128(survey.reward_amount.as_yoctonear()),
+            commission: U128(0),
+            participants_rewarded: survey.participants_rewarded,
+            legacy_rewards: survey.participants_rewarded,
+            status: if survey.is_canceled {
+                SurveyStatus::Canceled
+            } else {
+                SurveyStatus::Active
+            },
+            ft_contract_id: None,
+            starts_at: None,
+            ends_at: None,
+            storage_stake: NearToken::from_yoctonear(0),
+        }
+    }
+}
+
+impl SurveyV1 {
+    /// Unpaid budget of the survey that becomes part of the escrow.
+    pub(crate) fn escrowed_budget(&self) -> NearToken {
+        if self.is_canceled {
+            return NearToken::from_yoctonear(0);
+        }
+        NearToken::from_yoctonear(Survey::from(self.clone()).unused_budget().0)
+    }
+}
@@ -1,14 +1,130 @@
-use near_sdk::store::LookupMap;
-use near_sdk::{env, log, near, AccountId, NearToken, PanicOnDefault, Promise};
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::storage_management::StorageManagement;
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::store::{IterableSet, LookupMap, LookupSet, Vector};
+use near_sdk::{borsh, serde_json};
+use near_sdk::{
+    env, near, AccountId, BorshStorageKey, CryptoHash, CurveType, Gas, NearToken, PanicOnDefault,
+    Promise, PromiseError, PromiseOrValue, PromiseResult, PublicKey,
+};
+
+mod events;
+#[path = "../../shared/fees.rs"]
+mod fees;
+mod legacy;
+#[path = "../../shared/migrate.rs"]
+mod migrate;
+#[path = "../../shared/pause.rs"]
+mod pause;
+#[path = "../../shared/roles.rs"]
+mod roles;
+#[path = "../../shared/storage.rs"]
+mod storage;
+
+use events::SurveyEvent;
+use fees::{FeeConfig, SurveyFeeConfig};
+use legacy::SurveyV1;
+use pause::{PausableMethod, PauseState};
+use roles::{Role, RoleScope};
+use storage::StorageAccount;
+
+const TGAS: Gas = Gas::from_tgas(1); // 10e12yⓃ
+const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1); // required by ft_transfer
+const REWARD_BATCH_LIMIT: usize = 10; // most rewards scheduled per reward_participants call
+const REWARD_GAS: Gas = Gas::from_tgas(12); // transfer of one reward and its share of the callback
+const BATCH_GAS_RESERVE: Gas = Gas::from_tgas(30); // reward_participants itself and the callback base
+const DEFAULT_PAGE_LIMIT: u64 = 50; // entries returned by paginated views without a limit
+const GENERATED_ID_PREFIX: &str = "q-"; // reserved for IDs assigned by create_survey
+const MAX_COMMISSION_BPS: u16 = 10_000; // commission_bps of the whole reward budget
+const ARCHIVE_BATCH_LIMIT: u64 = 100; // participants deleted per archive_survey call
+const SURVEY_STORAGE_BYTES: u128 = 1_000; // upper bound of the storage create_survey adds
+const REWARD_STORAGE_BYTES: u64 = 1_000; // upper bound of the storage record_reward adds
+
+/// Prefixes of the state collections. Collections of one survey, account or
+/// role scope are keyed by the hash of its ID, so no ID can reach into the keys
+/// of another collection. State written by v1 keeps its byte prefixes.
+#[near(serializers = [borsh])]
+#[derive(BorshStorageKey)]
+pub enum StorageKey {
+    SurveyFeeConfigs,
+    RoleMembers,
+    RoleMemberSet { role: Role, scope_hash: CryptoHash },
+    Surveys,
+    ArchivedSurveys,
+    SurveysByCreator,
+    CreatorSurveyList { account_hash: CryptoHash },
+    SurveysUsersRewarded,
+    SurveyRewarded { survey_hash: CryptoHash },
+    Rewards,
+    SurveyParticipants,
+    SurveyParticipantList { survey_hash: CryptoHash },
+    ParticipantSurveys,
+    ParticipantSurveyList { account_hash: CryptoHash },
+    PendingPayouts,
+    ClaimableRewards,
+    SurveyProofKeys,
+    UsedProofNonces,
+    TokenTreasury,
+    StorageAccounts,
+    AcceptedTokens,
+    SurveyIds,
+    PendingRefunds,
+    TokenFeeConfigs,
+}
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
 pub struct Quizzler {
-    owner: AccountId,
+    /// `None` once the ownership was renounced.
+    owner: Option<AccountId>,
+    proposed_owner: Option<AccountId>,
     gas_station: AccountId,
-    managers: LookupMap<AccountId, bool>,
-    surveys: LookupMap<String, Survey>,
+    fee_config: FeeConfig,
+    survey_fee_configs: LookupMap<String, SurveyFeeConfig>,
+    /// Platform commission in basis points of the reward budget.
+    commission_bps: u16,
+    role_members: LookupMap<(Role, RoleScope), IterableSet<AccountId>>,
+    pause_state: PauseState,
+    /// Number used for the next contract-generated survey ID.
+    next_survey_number: u64,
+    surveys: LookupMap<String, VersionedSurvey>,
+    /// IDs of all surveys in creation order. Archived IDs are kept, so indexes
+    /// into the list do not change.
+    survey_ids: Vector<String>,
+    /// Summaries of surveys removed with `archive_survey`.
+    archived_surveys: LookupMap<String, SurveySummary>,
+    surveys_by_creator: LookupMap<AccountId, Vector<String>>,
     surveys_users_rewarded: LookupMap<String, LookupMap<AccountId, bool>>,
+    rewards: LookupMap<(String, AccountId), RewardRecord>,
+    survey_participants: LookupMap<String, Vector<AccountId>>,
+    participant_surveys: LookupMap<AccountId, Vector<String>>,
+    pending_payouts: LookupMap<AccountId, NearToken>,
+    /// Token refunds the survey creator did not accept, by creator and token.
+    pending_refunds: LookupMap<(AccountId, AccountId), U128>,
+    claimable_rewards: LookupMap<AccountId, Vec<ClaimableReward>>,
+    proof_public_key: Option<PublicKey>,
+    survey_proof_keys: LookupMap<String, PublicKey>,
+    used_proof_nonces: LookupSet<(String, u64)>,
+    /// NEAR owed to creators and participants of native surveys: unpaid rewards,
+    /// credited rewards and pending payouts, plus available storage balances.
+    escrow_balance: NearToken,
+    /// Native gas fees forwarded to the gas station so far.
+    fees_forwarded: NearToken,
+    /// Commission collected from native surveys and not withdrawn yet.
+    treasury: NearToken,
+    /// Commission collected from token surveys, per token contract.
+    token_treasury: LookupMap<AccountId, U128>,
+    /// NEP-145 storage balances, see `storage_deposit`.
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    /// NEP-141 contracts that token surveys can be funded with.
+    accepted_tokens: IterableSet<AccountId>,
+    /// Minimum gas fee of token surveys, in the smallest units of each token.
+    token_fee_configs: LookupMap<AccountId, FeeConfig>,
+    /// Set by `migrate` for a v1 state until `finish_v1_migration`. The budgets
+    /// of v1 surveys enter the escrow when they are moved, so there is no free
+    /// balance before.
+    v1_migration_pending: bool,
 }
 
 #[near(serializers = [json, borsh])]
@@ -16,9 +132,246 @@ pub struct Quizzler {
 pub struct Survey {
     survey_creator: AccountId,
     participants_limit: u64,
-    reward_amount: NearToken,
+    /// Reward per participant, in yoctoNEAR for native surveys or in the
+    /// smallest units of `ft_contract_id` for token surveys.
+    reward_amount: U128,
+    /// Platform commission charged on top of the reward budget, in the same
+    /// units as `reward_amount`. It is not refunded.
+    commission: U128,
+    participants_rewarded: u64,
+    /// Rewards paid by the first contract version, which left no reward records.
+    legacy_rewards: u64,
+    /// Stored status, `get_survey` returns the status derived with `current_status`.
+    status: SurveyStatus,
+    /// NEP-141 token the survey is funded with, `None` for native NEAR.
+    ft_contract_id: Option<AccountId>,
+    /// Reward window as block timestamps in nanoseconds, open-ended when `None`.
+    starts_at: Option<u64>,
+    ends_at: Option<u64>,
+    /// Storage cost of the survey and its rewards paid by the creator.
+    storage_stake: NearToken,
+}
+
+/// Stored form of a survey. Surveys moved over from a v1 state keep their
+/// layout until they are modified and are read through `latest`. Layouts of
+/// builds between v1 and the current one were never deployed and have no
+/// variant, so such a deployment cannot be upgraded in place.
+#[near(serializers = [borsh])]
+pub enum VersionedSurvey {
+    V1(SurveyV1),
+    V2(Survey),
+}
+
+impl VersionedSurvey {
+    fn latest(&self) -> Survey {
+        match self {
+            VersionedSurvey::V1(survey) => survey.clone().into(),
+            VersionedSurvey::V2(survey) => survey.clone(),
+        }
+    }
+
+    /// Upgrades the stored survey to the current layout before handing it out.
+    fn latest_mut(&mut self) -> &mut Survey {
+        if let VersionedSurvey::V1(survey) = self {
+            *self = VersionedSurvey::V2(survey.clone().into());
+        }
+        match self {
+            VersionedSurvey::V2(survey) => survey,
+            VersionedSurvey::V1(_) => unreachable!(),
+        }
+    }
+}
+
+impl From<Survey> for VersionedSurvey {
+    fn from(survey: Survey) -> Self {
+        VersionedSurvey::V2(survey)
+    }
+}
+
+/// Only `Active`, `Paused`, `Canceled` and `Expired` are ever stored, `Pending`,
+/// `Completed` and an `Expired` that was not finalized yet are derived.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SurveyStatus {
+    Pending,
+    Active,
+    Paused,
+    Completed,
+    Canceled,
+    Expired,
+}
+
+impl Survey {
+    fn current_status(&self) -> SurveyStatus {
+        if self.status != SurveyStatus::Active {
+            return self.status;
+        }
+
+        let now = env::block_timestamp();
+        if self.participants_rewarded >= self.participants_limit {
+            SurveyStatus::Completed
+        } else if self.ends_at.is_some_and(|ends_at| now >= ends_at) {
+            SurveyStatus::Expired
+        } else if self.starts_at.is_some_and(|starts_at| now < starts_at) {
+            SurveyStatus::Pending
+        } else {
+            SurveyStatus::Active
+        }
+    }
+
+    /// Moves the survey to `next`, panicking if the transition is not allowed
+    /// from its current status.
+    fn transition_to(&mut self, next: SurveyStatus) {
+        use SurveyStatus::*;
+
+        match (self.current_status(), next) {
+            (Pending | Active, Paused)
+            | (Paused, Active)
+            | (Pending | Active | Paused, Canceled)
+            | (Paused, Expired) => {}
+            (Expired, Expired) if self.status == Active => {}
+            (Expired, Expired) => panic!("Survey is finalized"),
+            (Canceled, _) => panic!("Survey is canceled"),
+            (Completed, _) => panic!("Survey is finished"),
+            (current, next) => panic!(
+                "Survey status cannot change from {:?} to {:?}",
+                current, next
+            ),
+        }
+        self.status = next;
+    }
+
+    fn assert_rewardable(&self) {
+        match self.status {
+            SurveyStatus::Canceled => panic!("Survey is canceled"),
+            SurveyStatus::Paused => panic!("Survey is paused"),
+            SurveyStatus::Expired => panic!("Survey has ended"),
+            _ => {}
+        }
+
+        let now = env::block_timestamp();
+        if let Some(starts_at) = self.starts_at {
+            assert!(now >= starts_at, "Survey has not started yet");
+        }
+        if let Some(ends_at) = self.ends_at {
+            assert!(now < ends_at, "Survey has ended");
+        }
+    }
+
+    fn unused_budget(&self) -> U128 {
+        let non_rewarded_users = self.participants_limit - self.participants_rewarded;
+        U128(
+            self.reward_amount
+                .0
+                .saturating_mul(non_rewarded_users as u128),
+        )
+    }
+}
+
+/// Breakdown of the contract balance returned by `get_accounting`.
+#[near(serializers = [json])]
+pub struct Accounting {
+    account_balance: NearToken,
+    storage_locked: NearToken,
+    escrow_balance: NearToken,
+    fees_forwarded: NearToken,
+    treasury: NearToken,
+    /// Balance that does not back any survey and can be withdrawn by the owner.
+    free_balance: NearToken,
+}
+
+/// What is kept of a survey after `archive_survey`. `storage_stake` is the part
+/// of the creator's storage stake that was not released yet.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct SurveySummary {
+    survey_creator: AccountId,
+    participants_limit: u64,
     participants_rewarded: u64,
-    is_canceled: bool,
+    reward_amount: U128,
+    ft_contract_id: Option<AccountId>,
+    status: SurveyStatus,
+    storage_stake: NearToken,
+    archived_at: u64,
+}
+
+/// Survey returned by the paginated views, together with its ID.
+#[near(serializers = [json])]
+pub struct SurveyEntry {
+    survey_id: String,
+    survey: Survey,
+}
+
+/// Reward paid out to a participant, `rewarded_at` is the block timestamp in
+/// nanoseconds. Native rewards that ended up as pending payouts are included.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct RewardRecord {
+    survey_id: String,
+    participant: AccountId,
+    amount: U128,
+    ft_contract_id: Option<AccountId>,
+    rewarded_at: u64,
+}
+
+/// Reward credited by a manager that the participant claims with `claim_reward`.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct ClaimableReward {
+    survey_id: String,
+    amount: U128,
+    ft_contract_id: Option<AccountId>,
+}
+
+#[near(serializers = [json])]
+pub enum RewardStatus {
+    Scheduled,
+    AlreadyRewarded,
+    LimitReached,
+    /// Left out of the bounded chunk, the account should be sent again.
+    NotProcessed,
+}
+
+#[near(serializers = [json])]
+pub struct RewardResult {
+    participant: AccountId,
+    status: RewardStatus,
+}
+
+/// Completion proof signed by the backend for `claim_with_proof`. The signed
+/// message is the borsh serialization of this struct, `expiry` is a block
+/// timestamp in nanoseconds.
+#[near(serializers = [borsh])]
+pub struct RewardProof {
+    contract_id: AccountId,
+    survey_id: String,
+    participant: AccountId,
+    nonce: u64,
+    expiry: u64,
+}
+
+/// Deposit quote of a NEAR-funded survey returned by `get_required_deposit`.
+#[near(serializers = [json])]
+pub struct RequiredDeposit {
+    gas_fee: NearToken,
+    rewards_budget: NearToken,
+    commission: NearToken,
+    /// Upper bound of the survey storage including the registration of a new
+    /// creator, the unused part is refunded.
+    storage_fee: NearToken,
+    total: NearToken,
+}
+
+/// Payload expected in the `msg` of `ft_transfer_call` to create a token survey.
+#[near(serializers = [json])]
+pub struct FtSurveyArgs {
+    /// Assigned by the contract when omitted, see `SurveyCreated`.
+    survey_id: Option<String>,
+    participants_limit: u64,
+    reward_amount: U128,
+    gas_fee: U128,
+    starts_at: Option<u64>,
+    ends_at: Option<u64>,
 }
 
 #[near]
@@ -27,61 +380,230 @@ impl Quizzler {
     pub fn new(gas_station: AccountId) -> Self {
         assert!(!env::state_exists(), "The contract is already initialized");
 
-        let predecessor = env::predecessor_account_id();
-        let mut managers = LookupMap::new(b"m");
-        managers.insert(predecessor.clone(), true);
+        Self::initial_state(env::predecessor_account_id(), gas_station)
+    }
 
-        Self {
-            owner: predecessor,
-            gas_station,
-            managers: managers,
-            surveys: LookupMap::new(b"s"),
-            surveys_users_rewarded: LookupMap::new(b"surveys_users_rewarded".to_vec()),
+    pub fn set_gas_station(&mut self, gas_station: AccountId) {
+        self.assert_admin();
+        self.gas_station = gas_station.clone();
+
+        SurveyEvent::GasStationChanged { gas_station }.emit();
+    }
+
+    pub fn get_gas_station(&self) -> AccountId {
+        self.gas_station.clone()
+    }
+
+    /// Accepts `ft_contract_id` for new token surveys or stops accepting it.
+    /// `ft_on_transfer` takes the `sender_id` of the token as the creator and
+    /// charges it storage, so only tokens that report it faithfully belong here.
+    pub fn set_token_accepted(&mut self, ft_contract_id: AccountId, accepted: bool) {
+        self.assert_admin();
+        if accepted {
+            self.accepted_tokens.insert(ft_contract_id.clone());
+        } else {
+            self.accepted_tokens.remove(&ft_contract_id);
+        }
+
+        SurveyEvent::TokenAcceptedChanged {
+            ft_contract_id,
+            accepted,
         }
+        .emit();
     }
 
-    pub fn set_manager(&mut self, manager: AccountId, status: bool) {
-        self.assert_owner();
-        self.managers.insert(manager, status);
+    pub fn get_accepted_tokens(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        self.accepted_tokens
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
     }
 
-    pub fn is_manager(&self, manager: AccountId) -> bool {
-        self.managers.get(&manager).unwrap_or(&false).clone()
+    /// Sets the gas fee token surveys pay in `ft_contract_id`, with amounts in
+    /// the smallest units of the token. Without a config the token cannot fund
+    /// surveys.
+    pub fn set_token_fee_config(
+        &mut self,
+        ft_contract_id: AccountId,
+        fee_config: Option<FeeConfig>,
+    ) {
+        self.assert_admin();
+        match &fee_config {
+            Some(fee_config) => self
+                .token_fee_configs
+                .insert(ft_contract_id.clone(), fee_config.clone()),
+            None => self.token_fee_configs.remove(&ft_contract_id),
+        };
+
+        SurveyEvent::TokenFeeConfigChanged {
+            ft_contract_id,
+            fee_config,
+        }
+        .emit();
     }
 
-    pub fn set_gas_station(&mut self, gas_station: AccountId) {
-        self.assert_owner();
-        self.gas_station = gas_station;
+    pub fn get_token_fee_config(&self, ft_contract_id: AccountId) -> Option<FeeConfig> {
+        self.token_fee_configs.get(&ft_contract_id).cloned()
     }
 
-    pub fn get_gas_station(&self) -> AccountId {
-        self.gas_station.clone()
+    /// Sets the platform commission taken from the reward budget of new surveys
+    /// and top-ups, in basis points.
+    pub fn set_commission(&mut self, commission_bps: u16) {
+        self.assert_admin();
+        assert!(
+            commission_bps <= MAX_COMMISSION_BPS,
+            "Commission cannot exceed {} basis points",
+            MAX_COMMISSION_BPS
+        );
+        self.commission_bps = commission_bps;
+
+        SurveyEvent::CommissionChanged { commission_bps }.emit();
+    }
+
+    pub fn get_commission(&self) -> u16 {
+        self.commission_bps
+    }
+
+    /// Returns the commission collected in yoctoNEAR, or in `ft_contract_id`.
+    pub fn get_treasury(&self, ft_contract_id: Option<AccountId>) -> U128 {
+        match ft_contract_id {
+            Some(ft_contract_id) => *self.token_treasury.get(&ft_contract_id).unwrap_or(&U128(0)),
+            None => U128(self.treasury.as_yoctonear()),
+        }
+    }
+
+    /// Withdraws collected commission in NEAR, or in `ft_contract_id`. The amount
+    /// returns to the treasury if the transfer fails.
+    pub fn withdraw_treasury(
+        &mut self,
+        amount: U128,
+        account_id: AccountId,
+        ft_contract_id: Option<AccountId>,
+    ) -> Promise {
+        self.assert_global_role(Role::FeeCollector);
+
+        let treasury = self.get_treasury(ft_contract_id.clone());
+        assert!(amount.0 <= treasury.0, "Not enough funds in the treasury");
+        self.set_treasury(&ft_contract_id, U128(treasury.0 - amount.0));
+
+        Self::transfer_funds(&ft_contract_id, account_id.clone(), amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(TGAS.saturating_mul(5))
+                .treasury_callback(account_id, amount, ft_contract_id),
+        )
+    }
+
+    pub fn set_proof_key(&mut self, public_key: Option<PublicKey>) {
+        self.assert_admin();
+        if let Some(public_key) = &public_key {
+            Self::assert_ed25519(public_key);
+        }
+        self.proof_public_key = public_key;
+    }
+
+    pub fn set_survey_proof_key(&mut self, survey_id: String, public_key: Option<PublicKey>) {
+        self.assert_not_paused(None);
+
+        self.assert_creator_or_admin(&survey_id);
+
+        match public_key {
+            Some(public_key) => {
+                Self::assert_ed25519(&public_key);
+                self.survey_proof_keys.insert(survey_id, public_key);
+            }
+            None => {
+                self.survey_proof_keys.remove(&survey_id);
+            }
+        }
+    }
+
+    /// Returns the key proofs of the survey are checked against, the survey key
+    /// takes precedence over the global one.
+    pub fn get_proof_key(&self, survey_id: String) -> Option<PublicKey> {
+        self.survey_proof_keys
+            .get(&survey_id)
+            .or(self.proof_public_key.as_ref())
+            .cloned()
+    }
+
+    /// Returns the deposit `create_survey` expects, pass `survey_id` and
+    /// `survey_creator` to apply a fee override. The storage part is an upper
+    /// bound, the rest is refunded.
+    pub fn get_required_deposit(
+        &self,
+        participants_limit: u64,
+        reward_amount: NearToken,
+        survey_id: Option<String>,
+        survey_creator: Option<AccountId>,
+    ) -> RequiredDeposit {
+        let gas_fee = self
+            .fee_config_for(survey_id.as_deref(), survey_creator.as_ref())
+            .gas_fee(participants_limit);
+        let rewards_budget = reward_amount.saturating_mul(participants_limit as u128);
+        let commission =
+            NearToken::from_yoctonear(self.commission_for(rewards_budget.as_yoctonear()));
+        let storage_fee = env::storage_byte_cost()
+            .saturating_mul(SURVEY_STORAGE_BYTES)
+            .saturating_add(self.storage_balance_bounds().min);
+
+        RequiredDeposit {
+            gas_fee,
+            rewards_budget,
+            commission,
+            storage_fee,
+            total: rewards_budget
+                .saturating_add(commission)
+                .saturating_add(gas_fee)
+                .saturating_add(storage_fee),
+        }
     }
 
+    /// Creates a native survey and returns its ID, which the contract assigns
+    /// when `survey_id` is `None`.
     #[payable]
     pub fn create_survey(
         &mut self,
-        survey_id: String,
+        survey_id: Option<String>,
         participants_limit: u64,
         reward_amount: NearToken,
         gas_fee: NearToken,
-    ) {
-        let attached_deposit = env::attached_deposit();
+        starts_at: Option<u64>,
+        ends_at: Option<u64>,
+    ) -> String {
+        self.assert_not_paused(Some(PausableMethod::Create));
+        let survey_id = self.claim_survey_id(survey_id);
 
-        let fee_needed = NearToken::from_yoctonear(15 * 10u128.pow(21))
-            .saturating_mul(participants_limit.clone() as u128);
+        let attached_deposit = env::attached_deposit();
+        let initial_storage = env::storage_usage();
         assert!(
-            gas_fee >= fee_needed,
-            "Gas fee is not sufficient. Required: {}, Attached: {}",
-            fee_needed,
-            gas_fee
+            participants_limit > 0,
+            "Participants limit must be greater than 0"
         );
 
-        let required_deposit =
-            (reward_amount.as_yoctonear() * participants_limit as u128) + gas_fee.as_yoctonear();
+        Self::assert_valid_window(starts_at, ends_at);
+
+        self.assert_gas_fee(
+            &survey_id,
+            &env::predecessor_account_id(),
+            participants_limit,
+            gas_fee,
+        );
+
+        let rewards_budget = reward_amount.saturating_mul(participants_limit as u128);
+        let commission =
+            NearToken::from_yoctonear(self.commission_for(rewards_budget.as_yoctonear()));
+        let required_deposit = rewards_budget
+            .saturating_add(commission)
+            .saturating_add(gas_fee);
 
         assert!(
-            attached_deposit >= NearToken::from_yoctonear(required_deposit),
+            attached_deposit >= required_deposit,
             "Attached deposit is not sufficient. Required: {}, Attached: {}",
             required_deposit,
             attached_deposit
@@ -90,110 +612,1532 @@ impl Quizzler {
         let survey = Survey {
             survey_creator: env::predecessor_account_id(),
             participants_limit,
-            reward_amount: reward_amount.into(),
+            reward_amount: U128(reward_amount.as_yoctonear()),
+            commission: U128(commission.as_yoctonear()),
             participants_rewarded: 0,
-            is_canceled: false,
+            legacy_rewards: 0,
+            status: SurveyStatus::Active,
+            ft_contract_id: None,
+            starts_at,
+            ends_at,
+            storage_stake: NearToken::from_yoctonear(0),
         };
-        self.surveys.insert(survey_id.clone(), survey);
+        self.insert_survey(survey_id.clone(), survey.into());
+
+        let storage_deposit = self.charge_survey_storage(
+            &survey_id,
+            initial_storage,
+            0,
+            attached_deposit.saturating_sub(required_deposit),
+        );
+        let required_deposit = required_deposit.saturating_add(storage_deposit);
+
+        self.escrow_balance = self.escrow_balance.saturating_add(rewards_budget);
+        self.accrue_commission(&None, U128(commission.as_yoctonear()));
+        self.fees_forwarded = self.fees_forwarded.saturating_add(gas_fee);
+        Promise::new(self.gas_station.clone()).transfer(gas_fee);
+
+        SurveyEvent::SurveyCreated {
+            survey_id: survey_id.clone(),
+            survey_creator: env::predecessor_account_id(),
+            participants_limit,
+            reward_amount: U128(reward_amount.as_yoctonear()),
+            gas_fee: U128(gas_fee.as_yoctonear()),
+            ft_contract_id: None,
+            starts_at,
+            ends_at,
+        }
+        .emit();
 
-        Promise::new(self.gas_station.clone()).transfer(gas_fee.into());
+        Self::refund_surplus(attached_deposit, required_deposit);
 
-        log!("survey_id: {}", survey_id);
-        log!("participants_limit: {}", participants_limit);
-        log!("reward_amount: {}", reward_amount);
-        log!("gas_fee: {}", gas_fee);
-        log!("survey_creator: {}", env::predecessor_account_id());
+        survey_id
     }
 
     pub fn get_survey(&self, survey_id: String) -> Survey {
-        self.surveys
+        let mut survey = self
+            .surveys
             .get(&survey_id)
             .expect("Survey does not exist")
-            .clone()
+            .latest();
+        survey.status = survey.current_status();
+        survey
+    }
+
+    /// Number of surveys created, archived ones included.
+    pub fn get_survey_count(&self) -> u64 {
+        self.survey_ids.len() as u64
+    }
+
+    /// Lists surveys in creation order. `from_index` is an index into all created
+    /// surveys and archived ones are skipped, so a page can hold fewer than `limit`
+    /// entries.
+    pub fn get_surveys(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<SurveyEntry> {
+        self.survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.surveys
+                    .get(survey_id)
+                    .map(|survey| Self::survey_entry(survey_id, survey))
+            })
+            .collect()
     }
 
-    pub fn reward_participant(&mut self, survey_id: String, participant: AccountId) {
-        self.assert_manager();
+    pub fn get_surveys_by_creator(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<SurveyEntry> {
+        let Some(survey_ids) = self.surveys_by_creator.get(&account_id) else {
+            return Vec::new();
+        };
+
+        survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.surveys
+                    .get(survey_id)
+                    .map(|survey| Self::survey_entry(survey_id, survey))
+            })
+            .collect()
+    }
+
+    /// Lists the surveys that currently accept rewards. `from_index` is an index
+    /// into all created surveys, so a page can hold fewer than `limit` entries.
+    pub fn get_active_surveys(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<SurveyEntry> {
+        self.survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.surveys
+                    .get(survey_id)
+                    .filter(|survey| survey.latest().current_status() == SurveyStatus::Active)
+                    .map(|survey| Self::survey_entry(survey_id, survey))
+            })
+            .collect()
+    }
+
+    /// Pays the reward to `participant`. Attach at least 30 TGas, the transfer and
+    /// `reward_callback` take 10 TGas each.
+    pub fn reward_participant(&mut self, survey_id: String, participant: AccountId) -> Promise {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        self.assert_survey_role(&survey_id, Role::Rewarder);
+
+        // The slot is reserved until `reward_callback` resolves the transfer.
+        let survey = self.reserve_reward(&survey_id, &participant);
+
+        Self::transfer_funds(
+            &survey.ft_contract_id,
+            participant.clone(),
+            survey.reward_amount,
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(TGAS.saturating_mul(10))
+                .reward_callback(survey_id, participant),
+        )
+    }
+
+    /// Rewards up to `REWARD_BATCH_LIMIT` participants in one call, fewer if the
+    /// attached gas does not cover them. Accounts that were already rewarded or
+    /// did not fit into the limits are skipped and reported.
+    pub fn reward_participants(
+        &mut self,
+        survey_id: String,
+        participants: Vec<AccountId>,
+    ) -> Vec<RewardResult> {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        self.assert_survey_role(&survey_id, Role::Rewarder);
+
+        let batch_limit = Self::reward_batch_limit();
+        let initial_storage = env::storage_usage();
 
         let survey = self
             .surveys
             .get_mut(&survey_id)
-            .expect("Survey does not exist");
-        assert!(!survey.is_canceled, "Survey is canceled");
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.assert_rewardable();
 
-        let prefix = format!("{}-r", survey_id).into_bytes();
+        let prefix = StorageKey::SurveyRewarded {
+            survey_hash: env::sha256_array(survey_id.as_bytes()),
+        };
         let rewarded = self
             .surveys_users_rewarded
-            .entry(survey_id)
+            .entry(survey_id.clone())
             .or_insert_with(|| LookupMap::new(prefix));
 
-        if !rewarded.contains_key(&participant) {
-            assert!(
-                survey.participants_rewarded < survey.participants_limit,
-                "Participant limit reached"
+        let mut scheduled = Vec::new();
+        let results = participants
+            .into_iter()
+            .map(|participant| {
+                let status = if rewarded.contains_key(&participant) {
+                    RewardStatus::AlreadyRewarded
+                } else if survey.participants_rewarded >= survey.participants_limit {
+                    RewardStatus::LimitReached
+                } else if scheduled.len() >= batch_limit {
+                    RewardStatus::NotProcessed
+                } else {
+                    survey.participants_rewarded += 1;
+                    rewarded.insert(participant.clone(), false);
+                    scheduled.push(participant.clone());
+                    RewardStatus::Scheduled
+                };
+                RewardResult {
+                    participant,
+                    status,
+                }
+            })
+            .collect();
+        let (ft_contract_id, reward_amount) = (survey.ft_contract_id.clone(), survey.reward_amount);
+
+        // The reward records are written in the callback, their storage is
+        // prepaid here.
+        self.charge_survey_storage(
+            &survey_id,
+            initial_storage,
+            REWARD_STORAGE_BYTES * scheduled.len() as u64,
+            NearToken::from_yoctonear(0),
+        );
+
+        let transfers = scheduled
+            .iter()
+            .map(|participant| {
+                Self::transfer_funds(&ft_contract_id, participant.clone(), reward_amount)
+            })
+            .reduce(Promise::and);
+
+        if let Some(transfers) = transfers {
+            let callback_gas = TGAS.saturating_mul(5 + 2 * scheduled.len() as u64);
+            transfers.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(callback_gas)
+                    .batch_reward_callback(survey_id, scheduled),
             );
+        }
 
-            Promise::new(participant.clone()).transfer(survey.reward_amount);
-            survey.participants_rewarded += 1;
-            rewarded.insert(participant, true);
-        } else {
-            panic!("Participant already rewarded");
+        results
+    }
+
+    /// Pays the reward of the caller against a completion proof signed by the
+    /// backend, without a manager being involved.
+    pub fn claim_with_proof(
+        &mut self,
+        survey_id: String,
+        nonce: u64,
+        expiry: u64,
+        signature: Base64VecU8,
+    ) -> Promise {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        let participant = env::predecessor_account_id();
+
+        assert!(env::block_timestamp() <= expiry, "Proof has expired");
+
+        let public_key = self
+            .get_proof_key(survey_id.clone())
+            .expect("No proof key registered");
+        let public_key: &[u8; 32] = public_key.as_bytes()[1..]
+            .try_into()
+            .expect("Invalid proof key");
+        let signature: [u8; 64] = signature.0.try_into().expect("Invalid signature length");
+
+        let proof = RewardProof {
+            contract_id: env::current_account_id(),
+            survey_id: survey_id.clone(),
+            participant: participant.clone(),
+            nonce,
+            expiry,
+        };
+        let message = borsh::to_vec(&proof).unwrap();
+        assert!(
+            env::ed25519_verify(&signature, &message, public_key),
+            "Invalid proof signature"
+        );
+
+        // The used nonce stays stored, its storage is charged like the reward's.
+        let initial_storage = env::storage_usage();
+        assert!(
+            self.used_proof_nonces.insert((survey_id.clone(), nonce)),
+            "Proof nonce already used"
+        );
+        self.charge_survey_storage(&survey_id, initial_storage, 0, NearToken::from_yoctonear(0));
+
+        let survey = self.reserve_reward(&survey_id, &participant);
+
+        Self::transfer_funds(
+            &survey.ft_contract_id,
+            participant.clone(),
+            survey.reward_amount,
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(TGAS.saturating_mul(10))
+                .reward_callback(survey_id, participant),
+        )
+    }
+
+    pub fn credit_reward(&mut self, survey_id: String, participant: AccountId) {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        self.assert_survey_role(&survey_id, Role::Rewarder);
+
+        let survey = self.reserve_reward(&survey_id, &participant);
+
+        let mut claimable = self
+            .claimable_rewards
+            .get(&participant)
+            .cloned()
+            .unwrap_or_default();
+        claimable.push(ClaimableReward {
+            survey_id: survey_id.clone(),
+            amount: survey.reward_amount,
+            ft_contract_id: survey.ft_contract_id,
+        });
+        self.claimable_rewards
+            .insert(participant.clone(), claimable);
+
+        SurveyEvent::RewardCredited {
+            survey_id,
+            participant,
+            amount: survey.reward_amount,
+        }
+        .emit();
+    }
+
+    /// Pays a credited reward to the caller. Attach at least 30 TGas, the transfer
+    /// and `claim_callback` take 10 TGas each.
+    pub fn claim_reward(&mut self, survey_id: String) -> Promise {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        let participant = env::predecessor_account_id();
+
+        let claimable = self
+            .claimable_rewards
+            .get_mut(&participant)
+            .expect("No claimable reward");
+        let index = claimable
+            .iter()
+            .position(|reward| reward.survey_id == survey_id)
+            .expect("No claimable reward");
+        let reward = claimable.swap_remove(index);
+        if claimable.is_empty() {
+            self.claimable_rewards.remove(&participant);
+        }
+
+        Self::transfer_funds(&reward.ft_contract_id, participant.clone(), reward.amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(TGAS.saturating_mul(10))
+                .claim_callback(participant, reward),
+        )
+    }
+
+    pub fn get_claimable(&self, account_id: AccountId) -> Vec<ClaimableReward> {
+        self.claimable_rewards
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Participants rewarded by the first contract version have no reward record
+    /// and are only found in the rewarded flags of the survey.
+    pub fn is_rewarded(&self, survey_id: String, account_id: AccountId) -> bool {
+        self.surveys_users_rewarded
+            .get(&survey_id)
+            .is_some_and(|rewarded| rewarded.get(&account_id) == Some(&true))
+            || self.rewards.contains_key(&(survey_id, account_id))
+    }
+
+    /// Lists the rewards paid out in a survey, in the order they were paid.
+    pub fn get_survey_participants(
+        &self,
+        survey_id: String,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<RewardRecord> {
+        let Some(participants) = self.survey_participants.get(&survey_id) else {
+            return Vec::new();
+        };
+
+        participants
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|participant| {
+                self.rewards
+                    .get(&(survey_id.clone(), participant.clone()))
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// Lists the rewards an account received, in the order they were paid.
+    pub fn get_participant_surveys(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<RewardRecord> {
+        let Some(survey_ids) = self.participant_surveys.get(&account_id) else {
+            return Vec::new();
+        };
+
+        survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.rewards
+                    .get(&(survey_id.clone(), account_id.clone()))
+                    .cloned()
+            })
+            .collect()
+    }
+
+    pub fn claim_pending_payout(&mut self) -> Promise {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        let account_id = env::predecessor_account_id();
+        let amount = self
+            .pending_payouts
+            .remove(&account_id)
+            .expect("No pending payout");
+
+        Promise::new(account_id.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(TGAS.saturating_mul(5))
+                .pending_payout_callback(account_id, amount),
+        )
+    }
+
+    pub fn get_pending_payout(&self, account_id: AccountId) -> NearToken {
+        *self
+            .pending_payouts
+            .get(&account_id)
+            .unwrap_or(&NearToken::from_yoctonear(0))
+    }
+
+    /// Pays the caller's pending refund in `ft_contract_id`. Attach at least
+    /// 30 TGas, the transfer and `pending_refund_callback` take 10 and 5 TGas.
+    pub fn claim_pending_refund(&mut self, ft_contract_id: AccountId) -> Promise {
+        self.assert_not_paused(None);
+
+        let account_id = env::predecessor_account_id();
+        let amount = self
+            .pending_refunds
+            .remove(&(account_id.clone(), ft_contract_id.clone()))
+            .expect("No pending refund");
+
+        Self::transfer_funds(&Some(ft_contract_id.clone()), account_id.clone(), amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(TGAS.saturating_mul(5))
+                .pending_refund_callback(account_id, ft_contract_id, amount),
+        )
+    }
+
+    pub fn get_pending_refund(&self, account_id: AccountId, ft_contract_id: AccountId) -> U128 {
+        *self
+            .pending_refunds
+            .get(&(account_id, ft_contract_id))
+            .unwrap_or(&U128(0))
+    }
+
+    pub fn get_accounting(&self) -> Accounting {
+        let account_balance = env::account_balance();
+        let storage_locked = env::storage_byte_cost().saturating_mul(env::storage_usage() as u128);
+        Accounting {
+            account_balance,
+            storage_locked,
+            escrow_balance: self.escrow_balance,
+            fees_forwarded: self.fees_forwarded,
+            treasury: self.treasury,
+            free_balance: if self.v1_migration_pending {
+                NearToken::from_yoctonear(0)
+            } else {
+                account_balance
+                    .saturating_sub(storage_locked)
+                    .saturating_sub(self.escrow_balance)
+                    .saturating_sub(self.treasury)
+            },
         }
     }
 
+    /// Withdraws NEAR that does not back any survey, see `get_accounting`.
+    pub fn emergency_withdraw(&mut self, amount: NearToken, account_id: AccountId) {
+        self.assert_global_role(Role::FeeCollector);
+        assert!(
+            amount <= self.get_accounting().free_balance,
+            "Not enough free balance"
+        );
+        Promise::new(account_id.clone()).transfer(amount);
+
+        SurveyEvent::near_withdrawal(account_id, amount).emit();
+    }
+
+    /// Cancels a survey and refunds its unused budget to the creator. Token
+    /// refunds need at least 30 TGas, see `refund_creator`.
     pub fn cancel_survey(&mut self, survey_id: String) {
+        self.assert_not_paused(Some(PausableMethod::Cancel));
+
         let survey_creator = {
-            let survey = self.surveys.get(&survey_id).expect("Survey does not exist");
+            let survey = self
+                .surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .latest();
             survey.survey_creator.clone()
         };
-        self.creator_or_manager(&survey_creator);
+        self.assert_creator_or_role(&survey_id, &survey_creator, &[Role::SurveyManager]);
 
         let survey = self
             .surveys
             .get_mut(&survey_id)
-            .expect("Survey does not exist");
+            .expect("Survey does not exist")
+            .latest_mut();
 
-        assert!(!survey.is_canceled, "Survey is canceled");
-        assert!(
-            survey.participants_limit > survey.participants_rewarded,
-            "Survey is finished"
-        );
+        survey.transition_to(SurveyStatus::Canceled);
 
-        let non_rewarded_users = survey.participants_limit - survey.participants_rewarded;
-        let refund_amount = survey
-            .reward_amount
-            .saturating_mul(non_rewarded_users as u128);
+        let refund_amount = survey.unused_budget();
+        let ft_contract_id = survey.ft_contract_id.clone();
+        Self::refund_creator(&ft_contract_id, survey_creator, refund_amount);
+        self.release_escrow(&ft_contract_id, refund_amount);
 
-        Promise::new(survey_creator.clone()).transfer(refund_amount.into());
-        survey.is_canceled = true;
+        SurveyEvent::SurveyCanceled {
+            survey_id,
+            refund_amount,
+        }
+        .emit();
     }
 
-    fn assert_owner(&self) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner,
-            "Only the owner can call this method"
-        );
-    }
+    /// Adds participants to a native survey. The deposit must cover their rewards
+    /// and gas fees, the gas fee is forwarded to the gas station.
+    #[payable]
+    pub fn top_up_survey(&mut self, survey_id: String, additional_participants: u64) {
+        self.assert_not_paused(Some(PausableMethod::Create));
 
-    fn assert_manager(&self) {
         assert!(
-            *self
-                .managers
-                .get(&env::predecessor_account_id())
-                .unwrap_or(&false),
-            "Only a manager can call this method"
+            additional_participants > 0,
+            "Additional participants must be greater than 0"
         );
-    }
 
-    fn creator_or_manager(&self, survey_creator: &AccountId) {
+        let survey_creator = {
+            let survey = self
+                .surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .latest();
+            survey.survey_creator.clone()
+        };
+        self.assert_creator_or_role(&survey_id, &survey_creator, &[Role::SurveyManager]);
+
+        // The flat base fee is only charged when the survey is created.
+        let gas_fee = self
+            .fee_config_for(Some(&survey_id), Some(&survey_creator))
+            .participants_fee(additional_participants);
+        let reward_amount = self
+            .surveys
+            .get(&survey_id)
+            .expect("Survey does not exist")
+            .latest()
+            .reward_amount;
+        let rewards_budget = reward_amount
+            .0
+            .saturating_mul(additional_participants as u128);
+        let commission = self.commission_for(rewards_budget);
+
+        let survey = self
+            .surveys
+            .get_mut(&survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        assert!(
+            survey.ft_contract_id.is_none(),
+            "Token surveys cannot be topped up with NEAR"
+        );
         assert!(
-            env::predecessor_account_id() == *survey_creator
-                || *self
-                    .managers
-                    .get(&env::predecessor_account_id())
-                    .unwrap_or(&false),
-            "Only the survey creator or a manager can call this method"
+            !matches!(
+                survey.current_status(),
+                SurveyStatus::Canceled | SurveyStatus::Expired
+            ),
+            "Survey is closed"
         );
+
+        let attached_deposit = env::attached_deposit();
+        let required_deposit = NearToken::from_yoctonear(rewards_budget.saturating_add(commission))
+            .saturating_add(gas_fee);
+
+        assert!(
+            attached_deposit >= required_deposit,
+            "Attached deposit is not sufficient. Required: {}, Attached: {}",
+            required_deposit,
+            attached_deposit
+        );
+
+        survey.participants_limit += additional_participants;
+        survey.commission = U128(survey.commission.0.saturating_add(commission));
+        let participants_limit = survey.participants_limit;
+
+        self.escrow_balance = self
+            .escrow_balance
+            .saturating_add(NearToken::from_yoctonear(rewards_budget));
+        self.accrue_commission(&None, U128(commission));
+        self.fees_forwarded = self.fees_forwarded.saturating_add(gas_fee);
+        Promise::new(self.gas_station.clone()).transfer(gas_fee);
+
+        SurveyEvent::SurveyResized {
+            survey_id,
+            participants_limit,
+            refund_amount: U128(0),
+        }
+        .emit();
+
+        Self::refund_surplus(attached_deposit, required_deposit);
+    }
+
+    /// Lowers the participants limit and refunds the freed rewards to the creator.
+    /// Token refunds need at least 30 TGas, see `refund_creator`.
+    pub fn shrink_survey(&mut self, survey_id: String, participants_limit: u64) {
+        let survey_creator = {
+            let survey = self
+                .surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .latest();
+            survey.survey_creator.clone()
+        };
+        self.assert_creator_or_role(&survey_id, &survey_creator, &[Role::SurveyManager]);
+
+        let survey = self
+            .surveys
+            .get_mut(&survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        assert!(
+            !matches!(
+                survey.current_status(),
+                SurveyStatus::Canceled | SurveyStatus::Expired
+            ),
+            "Survey is closed"
+        );
+        assert!(
+            participants_limit < survey.participants_limit,
+            "Participants limit can only be lowered"
+        );
+        assert!(
+            participants_limit >= survey.participants_rewarded,
+            "Participants limit cannot be lower than the rewarded participants"
+        );
+
+        let refund_amount = U128(
+            survey
+                .reward_amount
+                .0
+                .saturating_mul((survey.participants_limit - participants_limit) as u128),
+        );
+        survey.participants_limit = participants_limit;
+
+        let ft_contract_id = survey.ft_contract_id.clone();
+        Self::refund_creator(&ft_contract_id, survey_creator, refund_amount);
+        self.release_escrow(&ft_contract_id, refund_amount);
+
+        SurveyEvent::SurveyResized {
+            survey_id,
+            participants_limit,
+            refund_amount,
+        }
+        .emit();
+    }
+
+    pub fn pause_survey(&mut self, survey_id: String) {
+        self.set_survey_status(survey_id, SurveyStatus::Paused);
+    }
+
+    pub fn resume_survey(&mut self, survey_id: String) {
+        self.assert_not_paused(None);
+
+        self.set_survey_status(survey_id, SurveyStatus::Active);
+    }
+
+    /// Closes a survey after its `ends_at` and refunds the unused budget to the
+    /// creator. Anyone can call it, token refunds need at least 30 TGas.
+    pub fn finalize_survey(&mut self, survey_id: String) {
+        let survey = self
+            .surveys
+            .get_mut(&survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+
+        let ends_at = survey.ends_at.expect("Survey has no end time");
+        assert!(
+            env::block_timestamp() >= ends_at,
+            "Survey has not ended yet"
+        );
+        survey.transition_to(SurveyStatus::Expired);
+
+        let refund_amount = survey.unused_budget();
+        if refund_amount.0 > 0 {
+            let ft_contract_id = survey.ft_contract_id.clone();
+            Self::refund_creator(
+                &ft_contract_id,
+                survey.survey_creator.clone(),
+                refund_amount,
+            );
+            self.release_escrow(&ft_contract_id, refund_amount);
+        }
+
+        SurveyEvent::SurveyFinalized {
+            survey_id,
+            refund_amount,
+        }
+        .emit();
+    }
+
+    /// Removes a canceled, completed or finalized survey whose rewards are all
+    /// settled and keeps a `SurveySummary` of it. Its participant list is deleted
+    /// in chunks, so large surveys take several calls; returns `true` once done.
+    /// The reward records stay. The freed storage goes back to the creator's
+    /// storage balance up to their stake, the rest to the treasury.
+    pub fn archive_survey(&mut self, survey_id: String) -> bool {
+        self.assert_not_paused(None);
+        let initial_storage = env::storage_usage();
+
+        let survey_creator = match self.surveys.get(&survey_id) {
+            Some(survey) => survey.latest().survey_creator,
+            None => self
+                .archived_surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .survey_creator
+                .clone(),
+        };
+        self.assert_creator_or_role(&survey_id, &survey_creator, &[Role::SurveyManager]);
+
+        if self.surveys.contains_key(&survey_id) {
+            self.begin_archive(&survey_id);
+        } else if !self.survey_participants.contains_key(&survey_id) {
+            return true;
+        }
+
+        let mut archived = true;
+        if let Some(participants) = self.survey_participants.get_mut(&survey_id) {
+            for _ in 0..ARCHIVE_BATCH_LIMIT {
+                let Some(participant) = participants.pop() else {
+                    break;
+                };
+                if let Some(rewarded) = self.surveys_users_rewarded.get_mut(&survey_id) {
+                    rewarded.remove(&participant);
+                }
+            }
+            archived = participants.is_empty();
+        }
+        self.flush_survey_storage(&survey_id, &survey_creator);
+        if archived {
+            self.survey_participants.remove(&survey_id);
+            self.surveys_users_rewarded.remove(&survey_id);
+        }
+        self.surveys.flush();
+        self.archived_surveys.flush();
+        self.survey_participants.flush();
+        self.surveys_users_rewarded.flush();
+        self.survey_fee_configs.flush();
+        self.survey_proof_keys.flush();
+
+        let freed = Self::storage_freed_since(initial_storage);
+        let summary = self
+            .archived_surveys
+            .get_mut(&survey_id)
+            .expect("Survey does not exist");
+        let stake = freed.min(summary.storage_stake);
+        summary.storage_stake = summary.storage_stake.saturating_sub(stake);
+        let released = self.release_storage(&survey_creator, stake);
+        self.treasury = self.treasury.saturating_add(freed.saturating_sub(released));
+
+        if archived {
+            SurveyEvent::SurveyArchived { survey_id }.emit();
+        }
+        archived
+    }
+
+    pub fn get_archived_survey(&self, survey_id: String) -> Option<SurveySummary> {
+        self.archived_surveys.get(&survey_id).cloned()
+    }
+
+    #[private]
+    pub fn reward_callback(
+        &mut self,
+        survey_id: String,
+        participant: AccountId,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        self.resolve_reward(&survey_id, &participant, transfer_result.is_ok())
+    }
+
+    /// Resolves the joint transfers of `reward_participants`, whose results are
+    /// in the same order as `participants`.
+    #[private]
+    pub fn batch_reward_callback(
+        &mut self,
+        survey_id: String,
+        participants: Vec<AccountId>,
+    ) -> Vec<bool> {
+        participants
+            .iter()
+            .enumerate()
+            .map(|(index, participant)| {
+                let transferred = matches!(
+                    env::promise_result(index as u64),
+                    PromiseResult::Successful(_)
+                );
+                self.resolve_reward(&survey_id, participant, transferred)
+            })
+            .collect()
+    }
+
+    #[private]
+    pub fn pending_payout_callback(
+        &mut self,
+        account_id: AccountId,
+        amount: NearToken,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = transfer_result {
+            self.release_escrow(&None, U128(amount.as_yoctonear()));
+
+            SurveyEvent::near_withdrawal(account_id, amount).emit();
+            return true;
+        };
+
+        let pending = self.get_pending_payout(account_id.clone());
+        self.pending_payouts
+            .insert(account_id, pending.saturating_add(amount));
+        false
+    }
+
+    #[private]
+    pub fn treasury_callback(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        ft_contract_id: Option<AccountId>,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = transfer_result {
+            SurveyEvent::Withdrawal {
+                account_id,
+                amount,
+                ft_contract_id,
+            }
+            .emit();
+            return true;
+        };
+
+        self.accrue_commission(&ft_contract_id, amount);
+        false
+    }
+
+    /// Keeps a token gas fee the gas station did not accept, for example because
+    /// it is not registered with the token, in the token treasury.
+    #[private]
+    pub fn gas_fee_callback(
+        &mut self,
+        ft_contract_id: AccountId,
+        amount: U128,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = transfer_result {
+            return true;
+        };
+
+        self.accrue_commission(&Some(ft_contract_id), amount);
+        false
+    }
+
+    /// Keeps a token refund of a survey the creator did not accept, for example
+    /// because they are not registered with the token, as a pending refund.
+    #[private]
+    pub fn refund_callback(
+        &mut self,
+        account_id: AccountId,
+        ft_contract_id: AccountId,
+        amount: U128,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = transfer_result {
+            return true;
+        };
+
+        self.add_pending_refund((account_id, ft_contract_id), amount);
+        false
+    }
+
+    #[private]
+    pub fn pending_refund_callback(
+        &mut self,
+        account_id: AccountId,
+        ft_contract_id: AccountId,
+        amount: U128,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = transfer_result {
+            SurveyEvent::Withdrawal {
+                account_id,
+                amount,
+                ft_contract_id: Some(ft_contract_id),
+            }
+            .emit();
+            return true;
+        };
+
+        self.add_pending_refund((account_id, ft_contract_id), amount);
+        false
+    }
+
+    #[private]
+    pub fn claim_callback(
+        &mut self,
+        participant: AccountId,
+        reward: ClaimableReward,
+        #[callback_result] transfer_result: Result<(), PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = transfer_result {
+            if let Some(rewarded) = self.surveys_users_rewarded.get_mut(&reward.survey_id) {
+                rewarded.insert(participant.clone(), true);
+            }
+            self.record_reward(
+                &reward.survey_id,
+                &participant,
+                reward.amount,
+                &reward.ft_contract_id,
+            );
+            self.release_escrow(&reward.ft_contract_id, reward.amount);
+
+            SurveyEvent::ParticipantRewarded {
+                survey_id: reward.survey_id,
+                participant,
+                amount: reward.amount,
+            }
+            .emit();
+            return true;
+        };
+
+        SurveyEvent::RewardFailed {
+            survey_id: reward.survey_id.clone(),
+            participant: participant.clone(),
+        }
+        .emit();
+
+        let mut claimable = self
+            .claimable_rewards
+            .get(&participant)
+            .cloned()
+            .unwrap_or_default();
+        claimable.push(reward);
+        self.claimable_rewards.insert(participant, claimable);
+        false
+    }
+
+    /// Fresh state owned by `owner`, who passes every role check as an admin.
+    fn initial_state(owner: AccountId, gas_station: AccountId) -> Self {
+        Self {
+            owner: Some(owner),
+            proposed_owner: None,
+            gas_station,
+            fee_config: FeeConfig::default(),
+            survey_fee_configs: LookupMap::new(StorageKey::SurveyFeeConfigs),
+            commission_bps: 0,
+            role_members: LookupMap::new(StorageKey::RoleMembers),
+            pause_state: PauseState::default(),
+            next_survey_number: 1,
+            surveys: LookupMap::new(StorageKey::Surveys),
+            survey_ids: Vector::new(StorageKey::SurveyIds),
+            archived_surveys: LookupMap::new(StorageKey::ArchivedSurveys),
+            surveys_by_creator: LookupMap::new(StorageKey::SurveysByCreator),
+            surveys_users_rewarded: LookupMap::new(StorageKey::SurveysUsersRewarded),
+            rewards: LookupMap::new(StorageKey::Rewards),
+            survey_participants: LookupMap::new(StorageKey::SurveyParticipants),
+            participant_surveys: LookupMap::new(StorageKey::ParticipantSurveys),
+            pending_payouts: LookupMap::new(StorageKey::PendingPayouts),
+            pending_refunds: LookupMap::new(StorageKey::PendingRefunds),
+            claimable_rewards: LookupMap::new(StorageKey::ClaimableRewards),
+            proof_public_key: None,
+            survey_proof_keys: LookupMap::new(StorageKey::SurveyProofKeys),
+            used_proof_nonces: LookupSet::new(StorageKey::UsedProofNonces),
+            escrow_balance: NearToken::from_yoctonear(0),
+            fees_forwarded: NearToken::from_yoctonear(0),
+            treasury: NearToken::from_yoctonear(0),
+            token_treasury: LookupMap::new(StorageKey::TokenTreasury),
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            accepted_tokens: IterableSet::new(StorageKey::AcceptedTokens),
+            token_fee_configs: LookupMap::new(StorageKey::TokenFeeConfigs),
+            v1_migration_pending: false,
+        }
+    }
+
+    fn insert_survey(&mut self, survey_id: String, survey: VersionedSurvey) {
+        let survey_creator = survey.latest().survey_creator;
+        let prefix = StorageKey::CreatorSurveyList {
+            account_hash: env::sha256_array(survey_creator.as_bytes()),
+        };
+        self.surveys_by_creator
+            .entry(survey_creator)
+            .or_insert_with(|| Vector::new(prefix))
+            .push(survey_id.clone());
+        self.survey_ids.push(survey_id.clone());
+        self.surveys.insert(survey_id, survey);
+    }
+
+    /// Replaces the survey with its summary, once nothing can change it anymore.
+    fn begin_archive(&mut self, survey_id: &str) {
+        let survey = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest();
+        let status = survey.current_status();
+        match status {
+            SurveyStatus::Canceled | SurveyStatus::Completed => {}
+            SurveyStatus::Expired if survey.status == SurveyStatus::Expired => {}
+            SurveyStatus::Expired => panic!("Survey is not finalized"),
+            _ => panic!("Only canceled, completed or finalized surveys can be archived"),
+        }
+
+        // In-flight and credited rewards are counted but not recorded yet.
+        let settled = self
+            .survey_participants
+            .get(survey_id)
+            .map_or(0, |participants| participants.len() as u64);
+        assert!(
+            survey.participants_rewarded == settled + survey.legacy_rewards,
+            "Survey has rewards that are not settled"
+        );
+
+        self.surveys.remove(survey_id);
+        self.survey_fee_configs.remove(survey_id);
+        self.survey_proof_keys.remove(survey_id);
+        self.archived_surveys.insert(
+            survey_id.to_string(),
+            SurveySummary {
+                survey_creator: survey.survey_creator,
+                participants_limit: survey.participants_limit,
+                participants_rewarded: survey.participants_rewarded,
+                reward_amount: survey.reward_amount,
+                ft_contract_id: survey.ft_contract_id,
+                status,
+                storage_stake: survey.storage_stake,
+                archived_at: env::block_timestamp(),
+            },
+        );
+    }
+
+    fn survey_entry(survey_id: &str, survey: &VersionedSurvey) -> SurveyEntry {
+        let mut survey = survey.latest();
+        survey.status = survey.current_status();
+        SurveyEntry {
+            survey_id: survey_id.to_string(),
+            survey,
+        }
+    }
+
+    fn set_survey_status(&mut self, survey_id: String, status: SurveyStatus) {
+        let survey_creator = {
+            let survey = self
+                .surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .latest();
+            survey.survey_creator.clone()
+        };
+        self.assert_creator_or_role(
+            &survey_id,
+            &survey_creator,
+            &[Role::SurveyManager, Role::Pauser],
+        );
+
+        let survey = self
+            .surveys
+            .get_mut(&survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.transition_to(status);
+
+        SurveyEvent::SurveyStatusChanged { survey_id, status }.emit();
+    }
+
+    fn resolve_reward(
+        &mut self,
+        survey_id: &String,
+        participant: &AccountId,
+        transferred: bool,
+    ) -> bool {
+        let survey = self
+            .surveys
+            .get_mut(survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        let rewarded = self
+            .surveys_users_rewarded
+            .get_mut(survey_id)
+            .expect("Survey does not exist");
+
+        if transferred {
+            rewarded.insert(participant.clone(), true);
+            let (amount, ft_contract_id) = (survey.reward_amount, survey.ft_contract_id.clone());
+            self.record_reward(survey_id, participant, amount, &ft_contract_id);
+            self.release_escrow(&ft_contract_id, amount);
+
+            SurveyEvent::ParticipantRewarded {
+                survey_id: survey_id.clone(),
+                participant: participant.clone(),
+                amount,
+            }
+            .emit();
+            return true;
+        };
+
+        match survey.ft_contract_id {
+            Some(_) => {
+                // Token transfers mostly fail because the participant is not registered
+                // with the token, so the slot is released to be rewarded again.
+                survey.participants_rewarded -= 1;
+                rewarded.remove(participant);
+
+                // A cancel or finalize that ran while the transfer was in flight did
+                // not refund this slot. The callback has no gas for another transfer,
+                // so the creator claims it with `claim_pending_refund`.
+                if let (Some(ft_contract_id), SurveyStatus::Canceled | SurveyStatus::Expired) =
+                    (&survey.ft_contract_id, &survey.status)
+                {
+                    let refund = (survey.survey_creator.clone(), ft_contract_id.clone());
+                    let amount = survey.reward_amount;
+                    self.add_pending_refund(refund, amount);
+                }
+            }
+            None => {
+                // The refunded NEAR stays with the participant as a claimable payout.
+                rewarded.insert(participant.clone(), true);
+                let pending = *self
+                    .pending_payouts
+                    .get(participant)
+                    .unwrap_or(&NearToken::from_yoctonear(0));
+                self.pending_payouts.insert(
+                    participant.clone(),
+                    pending.saturating_add(NearToken::from_yoctonear(survey.reward_amount.0)),
+                );
+                let amount = survey.reward_amount;
+                self.record_reward(survey_id, participant, amount, &None);
+            }
+        }
+
+        SurveyEvent::RewardFailed {
+            survey_id: survey_id.clone(),
+            participant: participant.clone(),
+        }
+        .emit();
+        false
+    }
+
+    /// Releases NEAR that left the contract from the escrow, token surveys are
+    /// not part of it.
+    fn release_escrow(&mut self, ft_contract_id: &Option<AccountId>, amount: U128) {
+        if ft_contract_id.is_none() {
+            self.escrow_balance = self
+                .escrow_balance
+                .saturating_sub(NearToken::from_yoctonear(amount.0));
+        }
+    }
+
+    fn commission_for(&self, rewards_budget: u128) -> u128 {
+        rewards_budget.saturating_mul(self.commission_bps as u128) / MAX_COMMISSION_BPS as u128
+    }
+
+    fn accrue_commission(&mut self, ft_contract_id: &Option<AccountId>, commission: U128) {
+        if commission.0 == 0 {
+            return;
+        }
+        let treasury = self.get_treasury(ft_contract_id.clone());
+        self.set_treasury(
+            ft_contract_id,
+            U128(treasury.0.saturating_add(commission.0)),
+        );
+    }
+
+    fn set_treasury(&mut self, ft_contract_id: &Option<AccountId>, amount: U128) {
+        match ft_contract_id {
+            Some(ft_contract_id) => {
+                self.token_treasury.insert(ft_contract_id.clone(), amount);
+            }
+            None => self.treasury = NearToken::from_yoctonear(amount.0),
+        }
+    }
+
+    fn record_reward(
+        &mut self,
+        survey_id: &String,
+        participant: &AccountId,
+        amount: U128,
+        ft_contract_id: &Option<AccountId>,
+    ) {
+        self.rewards.insert(
+            (survey_id.clone(), participant.clone()),
+            RewardRecord {
+                survey_id: survey_id.clone(),
+                participant: participant.clone(),
+                amount,
+                ft_contract_id: ft_contract_id.clone(),
+                rewarded_at: env::block_timestamp(),
+            },
+        );
+
+        let prefix = StorageKey::SurveyParticipantList {
+            survey_hash: env::sha256_array(survey_id.as_bytes()),
+        };
+        self.survey_participants
+            .entry(survey_id.clone())
+            .or_insert_with(|| Vector::new(prefix))
+            .push(participant.clone());
+
+        let prefix = StorageKey::ParticipantSurveyList {
+            account_hash: env::sha256_array(participant.as_bytes()),
+        };
+        self.participant_surveys
+            .entry(participant.clone())
+            .or_insert_with(|| Vector::new(prefix))
+            .push(survey_id.clone());
+    }
+
+    fn reserve_reward(&mut self, survey_id: &String, participant: &AccountId) -> Survey {
+        let initial_storage = env::storage_usage();
+
+        let survey = self
+            .surveys
+            .get_mut(survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.assert_rewardable();
+
+        let prefix = StorageKey::SurveyRewarded {
+            survey_hash: env::sha256_array(survey_id.as_bytes()),
+        };
+        let rewarded = self
+            .surveys_users_rewarded
+            .entry(survey_id.clone())
+            .or_insert_with(|| LookupMap::new(prefix));
+
+        assert!(
+            !rewarded.contains_key(participant),
+            "Participant already rewarded"
+        );
+        assert!(
+            survey.participants_rewarded < survey.participants_limit,
+            "Participant limit reached"
+        );
+
+        survey.participants_rewarded += 1;
+        rewarded.insert(participant.clone(), false);
+        let survey = survey.clone();
+
+        // The reward record is written once the transfer resolved, its storage
+        // is prepaid here.
+        self.charge_survey_storage(
+            survey_id,
+            initial_storage,
+            REWARD_STORAGE_BYTES,
+            NearToken::from_yoctonear(0),
+        );
+        survey
+    }
+
+    /// Charges the storage added since `initial_storage` plus `prepaid_bytes`
+    /// for `survey_id` to its creator and adds it to the survey's stake. Panics
+    /// unless the creator's storage balance and `attached` cover it all.
+    /// Returns the part taken from `attached`.
+    fn charge_survey_storage(
+        &mut self,
+        survey_id: &str,
+        initial_storage: u64,
+        prepaid_bytes: u64,
+        attached: NearToken,
+    ) -> NearToken {
+        let survey_creator = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest()
+            .survey_creator;
+        self.flush_survey_storage(survey_id, &survey_creator);
+
+        let cost = Self::storage_cost_since(initial_storage)
+            .saturating_add(env::storage_byte_cost().saturating_mul(prepaid_bytes as u128));
+        let (charged, from_attached) = self.charge_storage(&survey_creator, cost, attached);
+        assert!(
+            charged == cost,
+            "Not enough storage balance. Required: {}, see storage_deposit",
+            cost
+        );
+
+        let survey = self
+            .surveys
+            .get_mut(survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.storage_stake = survey.storage_stake.saturating_add(charged);
+        from_attached
+    }
+
+    /// Writes the cached entries of the survey collections to storage, so that
+    /// `env::storage_usage` accounts for them.
+    fn flush_survey_storage(&mut self, survey_id: &str, survey_creator: &AccountId) {
+        if let Some(survey_ids) = self.surveys_by_creator.get_mut(survey_creator) {
+            survey_ids.flush();
+        }
+        if let Some(rewarded) = self.surveys_users_rewarded.get_mut(survey_id) {
+            rewarded.flush();
+        }
+        if let Some(participants) = self.survey_participants.get_mut(survey_id) {
+            participants.flush();
+        }
+        self.surveys.flush();
+        self.survey_ids.flush();
+        self.surveys_by_creator.flush();
+        self.surveys_users_rewarded.flush();
+        self.rewards.flush();
+        self.survey_participants.flush();
+        self.participant_surveys.flush();
+    }
+
+    fn transfer_funds(
+        ft_contract_id: &Option<AccountId>,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> Promise {
+        match ft_contract_id {
+            Some(ft_contract_id) => ext_ft_core::ext(ft_contract_id.clone())
+                .with_attached_deposit(ONE_YOCTO)
+                .with_static_gas(TGAS.saturating_mul(10))
+                .ft_transfer(receiver_id, amount, None),
+            None => Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount.0)),
+        }
+    }
+
+    /// Sends unused budget back to the survey creator. Token refunds are resolved
+    /// in `refund_callback`, which needs 5 TGas on top of the transfer.
+    fn refund_creator(ft_contract_id: &Option<AccountId>, survey_creator: AccountId, amount: U128) {
+        let transfer = Self::transfer_funds(ft_contract_id, survey_creator.clone(), amount);
+        if let Some(ft_contract_id) = ft_contract_id {
+            transfer.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(TGAS.saturating_mul(5))
+                    .refund_callback(survey_creator, ft_contract_id.clone(), amount),
+            );
+        }
+    }
+
+    fn add_pending_refund(&mut self, refund: (AccountId, AccountId), amount: U128) {
+        let pending = self
+            .pending_refunds
+            .get(&refund)
+            .map_or(0, |pending| pending.0);
+        self.pending_refunds
+            .insert(refund, U128(pending.saturating_add(amount.0)));
+    }
+
+    /// Number of rewards the prepaid gas covers, at most `REWARD_BATCH_LIMIT`.
+    fn reward_batch_limit() -> usize {
+        let available = env::prepaid_gas()
+            .saturating_sub(env::used_gas())
+            .saturating_sub(BATCH_GAS_RESERVE);
+        let batch_limit = (available.as_gas() / REWARD_GAS.as_gas()) as usize;
+        assert!(batch_limit > 0, "Not enough gas attached");
+        batch_limit.min(REWARD_BATCH_LIMIT)
+    }
+
+    fn assert_valid_window(starts_at: Option<u64>, ends_at: Option<u64>) {
+        if let Some(ends_at) = ends_at {
+            assert!(
+                ends_at > env::block_timestamp(),
+                "End time must be in the future"
+            );
+            if let Some(starts_at) = starts_at {
+                assert!(starts_at < ends_at, "Start time must be before end time");
+            }
+        }
+    }
+
+    fn assert_ed25519(public_key: &PublicKey) {
+        assert!(
+            matches!(public_key.curve_type(), CurveType::ED25519),
+            "Only ed25519 keys are supported"
+        );
+    }
+
+    /// Checks a survey ID chosen by the caller, or assigns the next free
+    /// `q-<number>` ID when there is none.
+    fn claim_survey_id(&mut self, survey_id: Option<String>) -> String {
+        if let Some(survey_id) = survey_id {
+            assert!(
+                !survey_id.starts_with(GENERATED_ID_PREFIX),
+                "Survey IDs starting with {} are assigned by the contract",
+                GENERATED_ID_PREFIX
+            );
+            self.assert_new_survey_id(&survey_id);
+            return survey_id;
+        }
+
+        // Surveys moved over from a v1 state may already use the prefix.
+        loop {
+            let survey_id = format!("{}{}", GENERATED_ID_PREFIX, self.next_survey_number);
+            self.next_survey_number += 1;
+            if !self.is_survey_id_taken(&survey_id) {
+                return survey_id;
+            }
+        }
+    }
+
+    /// Survey IDs must be valid subaccount names: 2 to 64 lowercase letters and
+    /// digits, separated by single `-` or `_`.
+    fn assert_new_survey_id(&self, survey_id: &str) {
+        assert!(
+            env::is_valid_account_id(survey_id.as_bytes()) && !survey_id.contains('.'),
+            "Invalid survey ID, use 2 to 64 lowercase letters and digits separated by - or _"
+        );
+        assert!(!self.is_survey_id_taken(survey_id), "Survey already exists");
+    }
+
+    /// IDs stay taken after archiving, and v1 surveys that were not moved with
+    /// `migrate_v1_surveys` yet keep theirs too.
+    fn is_survey_id_taken(&self, survey_id: &str) -> bool {
+        self.surveys.contains_key(survey_id)
+            || self.archived_surveys.contains_key(survey_id)
+            || Self::is_v1_survey(survey_id)
+    }
+}
+
+#[near]
+impl FungibleTokenReceiver for Quizzler {
+    /// Creates a survey funded with the transferred token, which must be accepted
+    /// with `set_token_accepted`. `msg` must hold `FtSurveyArgs`; any amount above
+    /// the required budget is returned.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_not_paused(Some(PausableMethod::Create));
+
+        let ft_contract_id = env::predecessor_account_id();
+        assert!(
+            self.accepted_tokens.contains(&ft_contract_id),
+            "Token is not accepted"
+        );
+
+        let args: FtSurveyArgs = serde_json::from_str(&msg).expect("Invalid survey arguments");
+        let survey_id = self.claim_survey_id(args.survey_id);
+        let initial_storage = env::storage_usage();
+        assert!(
+            args.participants_limit > 0,
+            "Participants limit must be greater than 0"
+        );
+
+        Self::assert_valid_window(args.starts_at, args.ends_at);
+
+        let fee_needed = self
+            .token_fee_configs
+            .get(&ft_contract_id)
+            .expect("Token has no fee config")
+            .gas_fee(args.participants_limit)
+            .as_yoctonear();
+        assert!(
+            args.gas_fee.0 >= fee_needed,
+            "Gas fee is not sufficient. Required: {}, Attached: {}",
+            fee_needed,
+            args.gas_fee.0
+        );
+
+        let rewards_budget = args
+            .reward_amount
+            .0
+            .checked_mul(args.participants_limit as u128)
+            .expect("Survey budget overflow");
+        let commission = self.commission_for(rewards_budget);
+        let required_deposit = rewards_budget
+            .checked_add(commission)
+            .and_then(|deposit| deposit.checked_add(args.gas_fee.0))
+            .expect("Survey budget overflow");
+
+        assert!(
+            amount.0 >= required_deposit,
+            "Attached amount is not sufficient. Required: {}, Attached: {}",
+            required_deposit,
+            amount.0
+        );
+
+        let survey = Survey {
+            survey_creator: sender_id.clone(),
+            participants_limit: args.participants_limit,
+            reward_amount: args.reward_amount,
+            commission: U128(commission),
+            participants_rewarded: 0,
+            legacy_rewards: 0,
+            status: SurveyStatus::Active,
+            ft_contract_id: Some(ft_contract_id.clone()),
+            starts_at: args.starts_at,
+            ends_at: args.ends_at,
+            storage_stake: NearToken::from_yoctonear(0),
+        };
+        self.insert_survey(survey_id.clone(), survey.into());
+        // No NEAR comes with a token transfer, the storage is paid from the
+        // creator's storage balance.
+        self.charge_survey_storage(&survey_id, initial_storage, 0, NearToken::from_yoctonear(0));
+        self.accrue_commission(&Some(ft_contract_id.clone()), U128(commission));
+
+        if args.gas_fee.0 > 0 {
+            Self::transfer_funds(
+                &Some(ft_contract_id.clone()),
+                self.gas_station.clone(),
+                args.gas_fee,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(TGAS.saturating_mul(5))
+                    .gas_fee_callback(ft_contract_id.clone(), args.gas_fee),
+            );
+        }
+
+        SurveyEvent::SurveyCreated {
+            survey_id,
+            survey_creator: sender_id.clone(),
+            participants_limit: args.participants_limit,
+            reward_amount: args.reward_amount,
+            gas_fee: args.gas_fee,
+            ft_contract_id: Some(ft_contract_id.clone()),
+            starts_at: args.starts_at,
+            ends_at: args.ends_at,
+        }
+        .emit();
+
+        let surplus = amount.0 - required_deposit;
+        if surplus > 0 {
+            SurveyEvent::Withdrawal {
+                account_id: sender_id,
+                amount: U128(surplus),
+                ft_contract_id: Some(ft_contract_id),
+            }
+            .emit();
+        }
+
+        PromiseOrValue::Value(U128(surplus))
     }
 }
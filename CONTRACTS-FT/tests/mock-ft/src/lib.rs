@@ -0,0 +1,81 @@
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::json_types::U128;
+use near_sdk::{near, AccountId, PanicOnDefault, PromiseOrValue};
+
+/// NEP-141 token without storage deposits: accounts are registered for free
+/// through `register_account`, so tests can leave one unregistered, or drop it
+/// with `unregister_account`, to make transfers to it fail.
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct MockFt {
+    token: FungibleToken,
+}
+
+#[near]
+impl MockFt {
+    #[init]
+    pub fn new(owner_id: AccountId, total_supply: U128) -> Self {
+        let mut token = FungibleToken::new(b"t".to_vec());
+        token.internal_register_account(&owner_id);
+        token.internal_deposit(&owner_id, total_supply.0);
+        Self { token }
+    }
+
+    pub fn register_account(&mut self, account_id: AccountId) {
+        if !self.token.accounts.contains_key(&account_id) {
+            self.token.internal_register_account(&account_id);
+        }
+    }
+
+    /// Drops the account together with its balance.
+    pub fn unregister_account(&mut self, account_id: AccountId) {
+        if let Some(balance) = self.token.accounts.remove(&account_id) {
+            self.token.total_supply -= balance;
+        }
+    }
+}
+
+#[near]
+impl FungibleTokenCore for MockFt {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        self.token.ft_transfer(receiver_id, amount, memo)
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.token.ft_transfer_call(receiver_id, amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near]
+impl FungibleTokenResolver for MockFt {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        self.token
+            .internal_ft_resolve_transfer(&sender_id, receiver_id, amount)
+            .0
+            .into()
+    }
+}
@@ -1,6 +1,9 @@
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_workspaces::sandbox;
+use near_workspaces::network::Sandbox;
 use near_workspaces::types::NearToken;
+use near_workspaces::{sandbox, Worker};
 use near_workspaces::{Account, AccountId, Contract};
 use serde_json::json;
 
@@ -9,6 +12,7 @@ const ONE_HUNDRED_NEAR: NearToken = NearToken::from_near(100);
 const SURVEY_ID: &str = "1dqwc-3gpomp-32oims-9ngn9ws";
 
 #[tokio::test]
+#[allow(clippy::bool_assert_comparison)]
 async fn test_check_in() -> Result<(), Box<dyn std::error::Error>> {
     let sandbox = sandbox().await?;
     let contract_wasm = near_workspaces::compile_project("./").await?;
@@ -59,6 +63,28 @@ async fn test_check_in() -> Result<(), Box<dyn std::error::Error>> {
         .json()?;
     assert_eq!(is_manager_outcome, true);
 
+    let has_role_outcome: bool = contract
+        .view("has_role")
+        .args_json(json!({"role": "Rewarder", "account_id": server_account.id()}))
+        .await?
+        .json()?;
+    assert!(has_role_outcome);
+
+    let get_role_members_outcome: Vec<AccountId> = contract
+        .view("get_role_members")
+        .args_json(json!({"role": "SurveyManager"}))
+        .await?
+        .json()?;
+    assert_eq!(get_role_members_outcome, vec![server_account.id().clone()]);
+
+    // Only admins can grant roles.
+    let outcome = user_account
+        .call(contract.id(), "grant_role")
+        .args_json(json!({"role": "Rewarder", "account_id": user_account.id(), "scope": {"Creator": user_account.id()}}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
     let get_gas_station_outcome: AccountId = contract
         .view("get_gas_station")
         .args_json(json!({}))
@@ -76,23 +102,103 @@ async fn test_check_in() -> Result<(), Box<dyn std::error::Error>> {
         .await?
         .unwrap();
 
+    register_storage(&user_account, &contract).await?;
     test_survey_create(&user_account, &contract).await?;
+    test_accounting(&deployer_account, &user_account, &contract).await?;
+    test_fee_config(&deployer_account, &user_account, &contract).await?;
+    test_pause(&deployer_account, &server_account, &user_account, &contract).await?;
     test_reward(&root, &rewarded_user_account_1, &server_account, &contract).await?;
     test_cancel(&root, &rewarded_user_account_1, &server_account, &contract).await?;
+    test_claim(&root, &user_account, &server_account, &contract).await?;
+    test_payout(&root, &user_account, &server_account, &contract).await?;
+    test_batch_reward(&user_account, &server_account, &contract).await?;
+    test_proof(&sandbox, &root, &deployer_account, &user_account, &contract).await?;
+    test_refund(&user_account, &contract).await?;
+    test_finalize(&sandbox, &root, &server_account, &contract).await?;
+    test_resize(&root, &server_account, &contract).await?;
+    test_commission(&deployer_account, &user_account, &contract).await?;
+    test_ownership(&deployer_account, &server_account, &contract).await?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct StorageBalance {
+    total: NearToken,
+    available: NearToken,
+}
+
+/// Prepays survey storage for `account` through NEP-145.
+async fn register_storage(
+    account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = account
+        .call(contract.id(), "storage_deposit")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
 
     Ok(())
 }
 
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct ClaimableReward {
+    survey_id: String,
+    amount: NearToken,
+    ft_contract_id: Option<AccountId>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 struct Survey {
     survey_creator: AccountId,
     participants_limit: u64,
     reward_amount: NearToken,
+    commission: NearToken,
     participants_rewarded: u64,
-    is_canceled: bool,
+    status: String,
+    ft_contract_id: Option<AccountId>,
+    storage_stake: NearToken,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct SurveySummary {
+    survey_creator: AccountId,
+    participants_rewarded: u64,
+    status: String,
+    storage_stake: NearToken,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct RewardRecord {
+    survey_id: String,
+    participant: AccountId,
+    amount: NearToken,
+    rewarded_at: u64,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct RewardResult {
+    participant: AccountId,
+    status: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct SurveyEntry {
+    survey_id: String,
+    survey: Survey,
 }
 
+#[allow(clippy::needless_borrow)]
 async fn test_survey_create(
     user_account: &Account,
     contract: &Contract,
@@ -111,6 +217,35 @@ async fn test_survey_create(
         .await?;
     assert!(outcome.is_success());
 
+    // A funded survey cannot be overwritten.
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": "Survey.1", "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"participants_limit": 0, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Participants limit must be greater than 0"));
+
     let get_survey_outcome: Survey = contract
         .view("get_survey")
         .args_json(json!({"survey_id": SURVEY_ID}))
@@ -125,11 +260,229 @@ async fn test_survey_create(
         reward_amount.to_string()
     );
     assert_eq!(get_survey_outcome.participants_rewarded, 0);
-    assert_eq!(get_survey_outcome.is_canceled, false);
+    assert_eq!(get_survey_outcome.status, "Active");
+    assert!(get_survey_outcome.ft_contract_id.is_none());
+    assert!(!get_survey_outcome.storage_stake.is_zero());
+
+    // The survey storage was taken from the prepaid storage balance.
+    let storage_balance_of_outcome: Option<StorageBalance> = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    let storage_balance = storage_balance_of_outcome.unwrap();
+    assert_eq!(storage_balance.total, NearToken::from_near(1));
+    assert!(storage_balance.available < NearToken::from_near(1));
+
+    let get_survey_count_outcome: u64 = contract
+        .view("get_survey_count")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_count_outcome, 1);
+
+    let get_surveys_by_creator_outcome: Vec<SurveyEntry> = contract
+        .view("get_surveys_by_creator")
+        .args_json(json!({"account_id": user_account.id(), "from_index": 0, "limit": 10}))
+        .await?
+        .json()?;
+    assert_eq!(get_surveys_by_creator_outcome.len(), 1);
+    assert_eq!(get_surveys_by_creator_outcome[0].survey_id, SURVEY_ID);
+
+    let get_active_surveys_outcome: Vec<SurveyEntry> = contract
+        .view("get_active_surveys")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(get_active_surveys_outcome.len(), 1);
+    assert_eq!(get_active_surveys_outcome[0].survey.status, "Active");
+
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct Accounting {
+    account_balance: NearToken,
+    storage_locked: NearToken,
+    escrow_balance: NearToken,
+    fees_forwarded: NearToken,
+    treasury: NearToken,
+    free_balance: NearToken,
+}
+
+async fn test_accounting(
+    owner_account: &Account,
+    user_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let accounting: Accounting = contract
+        .view("get_accounting")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    // The rewards budget and the storage balance the creator can still withdraw.
+    let storage_balance_of_outcome: Option<StorageBalance> = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(
+        accounting.escrow_balance.as_yoctonear(),
+        3 * 10u128.pow(22) + storage_balance_of_outcome.unwrap().available.as_yoctonear()
+    );
+    assert_eq!(accounting.fees_forwarded.as_yoctonear(), 10u128.pow(23));
+
+    // Survey budgets cannot be withdrawn.
+    let outcome = owner_account
+        .call(contract.id(), "emergency_withdraw")
+        .args_json(json!({"amount": accounting.free_balance.saturating_add(accounting.escrow_balance), "account_id": owner_account.id()}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct FeeConfig {
+    per_participant_fee: NearToken,
+    base_fee: NearToken,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct RequiredDeposit {
+    gas_fee: NearToken,
+    rewards_budget: NearToken,
+    commission: NearToken,
+    storage_fee: NearToken,
+    total: NearToken,
+}
+
+async fn test_fee_config(
+    owner_account: &Account,
+    user_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let get_fee_config_outcome: FeeConfig = contract
+        .view("get_fee_config")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_fee_config_outcome,
+        FeeConfig {
+            per_participant_fee: NearToken::from_millinear(15),
+            base_fee: NearToken::from_yoctonear(0),
+        }
+    );
+
+    let enterprise_fee_config = FeeConfig {
+        per_participant_fee: NearToken::from_yoctonear(0),
+        base_fee: NearToken::from_near(1),
+    };
+
+    // Only admins can change fees.
+    let outcome = user_account
+        .call(contract.id(), "set_survey_fee_config")
+        .args_json(json!({"survey_id": "enterprise", "survey_creator": user_account.id(), "fee_config": enterprise_fee_config}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = owner_account
+        .call(contract.id(), "set_survey_fee_config")
+        .args_json(json!({"survey_id": "enterprise", "survey_creator": user_account.id(), "fee_config": enterprise_fee_config}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_fee_config_outcome: FeeConfig = contract
+        .view("get_fee_config")
+        .args_json(json!({"survey_id": "enterprise", "survey_creator": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(get_fee_config_outcome, enterprise_fee_config);
+
+    // The override only applies to the creator it was set for.
+    let get_fee_config_outcome: FeeConfig = contract
+        .view("get_fee_config")
+        .args_json(json!({"survey_id": "enterprise", "survey_creator": owner_account.id()}))
+        .await?
+        .json()?;
+    assert_ne!(get_fee_config_outcome, enterprise_fee_config);
+
+    let get_required_deposit_outcome: RequiredDeposit = contract
+        .view("get_required_deposit")
+        .args_json(json!({"participants_limit": 10, "reward_amount": NearToken::from_millinear(100), "survey_id": "enterprise", "survey_creator": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_required_deposit_outcome.gas_fee,
+        NearToken::from_near(1)
+    );
+    assert_eq!(
+        get_required_deposit_outcome.total,
+        NearToken::from_near(2).saturating_add(get_required_deposit_outcome.storage_fee)
+    );
+
+    let get_required_deposit_outcome: RequiredDeposit = contract
+        .view("get_required_deposit")
+        .args_json(
+            json!({"participants_limit": 10, "reward_amount": NearToken::from_millinear(100)}),
+        )
+        .await?
+        .json()?;
+    assert_eq!(
+        get_required_deposit_outcome.gas_fee,
+        NearToken::from_millinear(150)
+    );
+
+    Ok(())
+}
+
+async fn test_pause(
+    owner_account: &Account,
+    server_account: &Account,
+    user_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = owner_account
+        .call(contract.id(), "set_method_paused")
+        .args_json(json!({"method": "Reward", "paused": true}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": user_account.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Rewards are paused");
+
+    let outcome = owner_account
+        .call(contract.id(), "set_method_paused")
+        .args_json(json!({"method": "Reward", "paused": false}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Only the owner, admins and pausers can pause the contract.
+    let outcome = user_account
+        .call(contract.id(), "set_paused")
+        .args_json(json!({"paused": true}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
 
     Ok(())
 }
 
+#[allow(clippy::needless_borrow)]
 async fn test_reward(
     root_account: &Account,
     rewarded_user_account_1: &Account,
@@ -141,6 +494,7 @@ async fn test_reward(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_1.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -154,6 +508,7 @@ async fn test_reward(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_1.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(!outcome.is_success());
@@ -161,6 +516,25 @@ async fn test_reward(
         .into_result()
         .expect_err("Participant already rewarded");
 
+    let is_rewarded_outcome: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": SURVEY_ID, "account_id": rewarded_user_account_1.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded_outcome);
+
+    let get_participant_surveys_outcome: Vec<RewardRecord> = contract
+        .view("get_participant_surveys")
+        .args_json(json!({"account_id": rewarded_user_account_1.id()}))
+        .await?
+        .json()?;
+    assert_eq!(get_participant_surveys_outcome.len(), 1);
+    assert_eq!(get_participant_surveys_outcome[0].survey_id, SURVEY_ID);
+    assert_eq!(
+        get_participant_surveys_outcome[0].amount.as_yoctonear(),
+        reward_amount
+    );
+
     let rewarded_user_account_2 = root_account
         .create_subaccount("rewarded_user_2")
         .initial_balance(ONE_HUNDRED_NEAR)
@@ -171,6 +545,7 @@ async fn test_reward(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_2.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -185,6 +560,7 @@ async fn test_reward(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_3.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -199,6 +575,7 @@ async fn test_reward(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_4.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(!outcome.is_success());
@@ -209,6 +586,7 @@ async fn test_reward(
     Ok(())
 }
 
+#[allow(clippy::needless_borrow)]
 async fn test_cancel(
     root_account: &Account,
     rewarded_user_account_1: &Account,
@@ -227,6 +605,7 @@ async fn test_cancel(
         .transact()
         .await?
         .unwrap();
+    register_storage(&business_user_account_1, contract).await?;
 
     let outcome = business_user_account_1
         .call(&contract.id(), "create_survey")
@@ -240,11 +619,41 @@ async fn test_cancel(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": NEW_SURVEY_ID, "participant": rewarded_user_account_1.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(outcome.is_success());
     // println!("{:#?}", outcome.clone().into_result());
 
+    let outcome = business_user_account_1
+        .call(contract.id(), "pause_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let rewarded_user_account_5 = root_account
+        .create_subaccount("rewarded_user_5")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID, "participant": rewarded_user_account_5.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Survey is paused");
+
+    let outcome = business_user_account_1
+        .call(contract.id(), "resume_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
     let user_balance_prev = business_user_account_1.view_account().await?.balance;
     let outcome = server_account
         .call(&contract.id(), "cancel_survey")
@@ -269,10 +678,1571 @@ async fn test_cancel(
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": NEW_SURVEY_ID, "participant": rewarded_user_account_6.id()}))
+        .max_gas()
         .transact()
         .await?;
     assert!(!outcome.is_success());
     outcome.into_result().expect_err("Survey is canceled");
 
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.status, "Canceled");
+
+    // Archiving frees the survey storage back to the creator.
+    let storage_balance_prev: StorageBalance = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": business_user_account_1.id()}))
+        .await?
+        .json()?;
+    let outcome = business_user_account_1
+        .call(contract.id(), "archive_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+    let storage_balance_after: StorageBalance = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": business_user_account_1.id()}))
+        .await?
+        .json()?;
+    assert!(storage_balance_after.available > storage_balance_prev.available);
+
+    let summary: Option<SurveySummary> = contract
+        .view("get_archived_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .await?
+        .json()?;
+    let summary = summary.expect("Survey is archived");
+    assert_eq!(summary.status, "Canceled");
+    assert_eq!(summary.participants_rewarded, 1);
+    assert!(summary.storage_stake < get_survey_outcome.storage_stake);
+
+    let outcome = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .await;
+    assert!(outcome.is_err());
+
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID, "account_id": rewarded_user_account_1.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    // Archiving again changes nothing.
+    let outcome = business_user_account_1
+        .call(contract.id(), "archive_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"survey_archived\"")));
+    assert!(outcome.json::<bool>()?);
+
+    let get_surveys_outcome: Vec<SurveyEntry> = contract
+        .view("get_surveys")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert!(get_surveys_outcome
+        .iter()
+        .all(|entry| entry.survey_id != NEW_SURVEY_ID));
+
+    // The ID stays taken.
+    let outcome = business_user_account_1
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": NEW_SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    Ok(())
+}
+
+async fn test_claim(
+    root_account: &Account,
+    user_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const CLAIM_SURVEY_ID: &str = "3jdkwo-pq83na-x8s0qm-1lsmv02";
+    let fee_amount = 10u128.pow(23);
+    let reward_amount = 10u128.pow(22);
+    let participants_limit = 3u128;
+    let deposit_amount = fee_amount + (reward_amount * participants_limit);
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let claiming_user_account = root_account
+        .create_subaccount("claiming_user")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let outcome = server_account
+        .call(contract.id(), "credit_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "participant": claiming_user_account.id()}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "participant": claiming_user_account.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    outcome
+        .into_result()
+        .expect_err("Participant already rewarded");
+
+    let claimable: Vec<ClaimableReward> = contract
+        .view("get_claimable")
+        .args_json(json!({"account_id": claiming_user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(claimable.len(), 1);
+    assert_eq!(claimable[0].survey_id, CLAIM_SURVEY_ID);
+    assert_eq!(claimable[0].amount.as_yoctonear(), reward_amount);
+
+    let outcome = claiming_user_account
+        .call(contract.id(), "claim_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let claimable: Vec<ClaimableReward> = contract
+        .view("get_claimable")
+        .args_json(json!({"account_id": claiming_user_account.id()}))
+        .await?
+        .json()?;
+    assert!(claimable.is_empty());
+
+    let outcome = claiming_user_account
+        .call(contract.id(), "claim_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("No claimable reward");
+
+    Ok(())
+}
+
+async fn test_payout(
+    root_account: &Account,
+    user_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const PAYOUT_SURVEY_ID: &str = "7fhqla-0zmc3e-pq41bv-6vnek29";
+    let fee_amount = 10u128.pow(23);
+    let reward_amount = 10u128.pow(22);
+    let participants_limit = 3u128;
+    let deposit_amount = fee_amount + (reward_amount * participants_limit);
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": PAYOUT_SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The transfer to an account that does not exist yet bounces back and is
+    // kept for the participant.
+    let ghost_id: AccountId = format!("ghost.{}", root_account.id()).parse()?;
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": PAYOUT_SURVEY_ID, "participant": ghost_id}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.json::<bool>()?);
+
+    let pending: NearToken = contract
+        .view("get_pending_payout")
+        .args_json(json!({"account_id": ghost_id}))
+        .await?
+        .json()?;
+    assert_eq!(pending.as_yoctonear(), reward_amount);
+
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": PAYOUT_SURVEY_ID, "account_id": ghost_id}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    let outcome = root_account
+        .call(contract.id(), "claim_pending_payout")
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("No pending payout");
+
+    // The account is deleted before the payout reaches it, so the payout is
+    // put back.
+    let ghost_account = root_account
+        .create_subaccount("ghost")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+    let claim = ghost_account
+        .call(contract.id(), "claim_pending_payout")
+        .max_gas()
+        .transact_async()
+        .await?;
+    let outcome = ghost_account.delete_account(root_account.id()).await?;
+    assert!(outcome.is_success());
+    assert!(!claim.await?.json::<bool>()?);
+
+    let pending: NearToken = contract
+        .view("get_pending_payout")
+        .args_json(json!({"account_id": ghost_id}))
+        .await?
+        .json()?;
+    assert_eq!(pending.as_yoctonear(), reward_amount);
+
+    let ghost_account = root_account
+        .create_subaccount("ghost")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+    let balance_prev = ghost_account.view_account().await?.balance;
+    let outcome = ghost_account
+        .call(contract.id(), "claim_pending_payout")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+    let balance_after = ghost_account.view_account().await?.balance;
+    assert!(balance_after > balance_prev);
+
+    let pending: NearToken = contract
+        .view("get_pending_payout")
+        .args_json(json!({"account_id": ghost_id}))
+        .await?
+        .json()?;
+    assert!(pending.is_zero());
+
+    Ok(())
+}
+
+async fn test_batch_reward(
+    user_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const BATCH_SURVEY_ID: &str = "8kcn2m-wq01bz-ya7d4e-3hsp5rt";
+    let reward_amount = NearToken::from_millinear(1);
+    let participants_limit = 11u64;
+
+    let required_deposit: RequiredDeposit = contract
+        .view("get_required_deposit")
+        .args_json(json!({"participants_limit": participants_limit, "reward_amount": reward_amount, "survey_id": BATCH_SURVEY_ID}))
+        .await?
+        .json()?;
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants_limit": participants_limit, "reward_amount": reward_amount, "gas_fee": required_deposit.gas_fee}))
+        .deposit(required_deposit.total)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Implicit accounts, created by the reward transfer.
+    let participants = (1..=12)
+        .map(|index| format!("{:064x}", index).parse())
+        .collect::<Result<Vec<AccountId>, _>>()?;
+
+    // 10 transfers fit into one call, the duplicate is skipped and the last two
+    // are left for the next call.
+    let mut batch = vec![participants[0].clone()];
+    batch.extend(participants.iter().cloned());
+    let outcome = server_account
+        .call(contract.id(), "reward_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants": batch}))
+        .max_gas()
+        .transact()
+        .await?;
+    let results: Vec<RewardResult> = outcome.json()?;
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|result| result.status.as_str())
+        .collect();
+    assert_eq!(results.len(), 13);
+    assert_eq!(statuses[0], "Scheduled");
+    assert_eq!(statuses[1], "AlreadyRewarded");
+    assert!(statuses[2..11].iter().all(|status| *status == "Scheduled"));
+    assert_eq!(statuses[11..], ["NotProcessed", "NotProcessed"]);
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants": participants[9..]}))
+        .max_gas()
+        .transact()
+        .await?;
+    let results: Vec<RewardResult> = outcome.json()?;
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|result| result.status.as_str())
+        .collect();
+    assert_eq!(statuses, ["AlreadyRewarded", "Scheduled", "LimitReached"]);
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 11);
+    assert_eq!(get_survey_outcome.status, "Completed");
+
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "account_id": participants[10]}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    Ok(())
+}
+
+/// Mirror of the contract's `RewardProof`, the borsh form is what gets signed.
+#[derive(near_sdk::borsh::BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+struct RewardProof {
+    contract_id: String,
+    survey_id: String,
+    participant: String,
+    nonce: u64,
+    expiry: u64,
+}
+
+fn keypair(seed: u8) -> Result<Keypair, Box<dyn std::error::Error>> {
+    let secret = SecretKey::from_bytes(&[seed; 32])?;
+    let public = PublicKey::from(&secret);
+    Ok(Keypair { secret, public })
+}
+
+/// Signs the proof the way the backend does and returns the `claim_with_proof` arguments.
+fn sign_proof(
+    signing_key: &Keypair,
+    contract: &Contract,
+    survey_id: &str,
+    participant: &Account,
+    nonce: u64,
+    expiry: u64,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let proof = RewardProof {
+        contract_id: contract.id().to_string(),
+        survey_id: survey_id.to_string(),
+        participant: participant.id().to_string(),
+        nonce,
+        expiry,
+    };
+    let signature = signing_key.sign(&near_sdk::borsh::to_vec(&proof)?);
+    Ok(json!({
+        "survey_id": survey_id,
+        "nonce": nonce,
+        "expiry": expiry,
+        "signature": Base64VecU8::from(signature.to_bytes().to_vec()),
+    }))
+}
+
+async fn test_proof(
+    sandbox: &Worker<Sandbox>,
+    root_account: &Account,
+    owner_account: &Account,
+    user_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const PROOF_SURVEY_ID: &str = "9vzr5x-lk20ej-mc8a3f-0tyqw61";
+    let fee_amount = 10u128.pow(23);
+    let reward_amount = 10u128.pow(22);
+    let participants_limit = 3u128;
+    let deposit_amount = fee_amount + (reward_amount * participants_limit);
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": PROOF_SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let signing_key = keypair(7)?;
+    let public_key = near_sdk::PublicKey::from_parts(
+        near_sdk::CurveType::ED25519,
+        signing_key.public.to_bytes().to_vec(),
+    )?;
+    let outcome = owner_account
+        .call(contract.id(), "set_proof_key")
+        .args_json(json!({"public_key": public_key}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let proving_user_account = root_account
+        .create_subaccount("proving_user")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+    let expiry = sandbox.view_block().await?.timestamp() + 3600 * 10u64.pow(9);
+
+    // A proof signed by another key is rejected.
+    let other_key = keypair(8)?;
+    let args = sign_proof(
+        &other_key,
+        contract,
+        PROOF_SURVEY_ID,
+        &proving_user_account,
+        1,
+        expiry,
+    )?;
+    let outcome = proving_user_account
+        .call(contract.id(), "claim_with_proof")
+        .args_json(args)
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Invalid proof signature");
+
+    // So is a proof that has expired.
+    let expired = sandbox.view_block().await?.timestamp() - 1;
+    let args = sign_proof(
+        &signing_key,
+        contract,
+        PROOF_SURVEY_ID,
+        &proving_user_account,
+        1,
+        expired,
+    )?;
+    let outcome = proving_user_account
+        .call(contract.id(), "claim_with_proof")
+        .args_json(args)
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Proof has expired");
+
+    let args = sign_proof(
+        &signing_key,
+        contract,
+        PROOF_SURVEY_ID,
+        &proving_user_account,
+        1,
+        expiry,
+    )?;
+    let balance_prev = proving_user_account.view_account().await?.balance;
+    let outcome = proving_user_account
+        .call(contract.id(), "claim_with_proof")
+        .args_json(args.clone())
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+    let balance_after = proving_user_account.view_account().await?.balance;
+    assert!(balance_after > balance_prev);
+
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": PROOF_SURVEY_ID, "account_id": proving_user_account.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    // The same proof cannot be used twice.
+    let outcome = proving_user_account
+        .call(contract.id(), "claim_with_proof")
+        .args_json(args)
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Proof nonce already used");
+
+    // Nor can the proof of another participant.
+    let args = sign_proof(
+        &signing_key,
+        contract,
+        PROOF_SURVEY_ID,
+        &proving_user_account,
+        2,
+        expiry,
+    )?;
+    let outcome = root_account
+        .call(contract.id(), "claim_with_proof")
+        .args_json(args)
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Invalid proof signature");
+
+    Ok(())
+}
+
+async fn test_refund(
+    user_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const REFUND_SURVEY_ID: &str = "5bqe7c-rw62nd-hz0k4u-2mfla83";
+    let reward_amount = NearToken::from_millinear(10);
+    let participants_limit = 3u64;
+    let surplus = NearToken::from_near(1);
+
+    let required_deposit: RequiredDeposit = contract
+        .view("get_required_deposit")
+        .args_json(json!({"participants_limit": participants_limit, "reward_amount": reward_amount, "survey_id": REFUND_SURVEY_ID}))
+        .await?
+        .json()?;
+
+    // The storage comes from the prepaid storage balance, so the whole surplus
+    // and the quoted storage fee go back to the creator.
+    let balance_prev = user_account.view_account().await?.balance;
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": REFUND_SURVEY_ID, "participants_limit": participants_limit, "reward_amount": reward_amount, "gas_fee": required_deposit.gas_fee}))
+        .deposit(required_deposit.total.saturating_add(surplus))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let refund = surplus.saturating_add(required_deposit.storage_fee);
+    let withdrawal =
+        json!({"account_id": user_account.id(), "amount": refund, "ft_contract_id": null})
+            .to_string();
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"withdrawal\"") && log.contains(&withdrawal)));
+
+    let balance_after = user_account.view_account().await?.balance;
+    let spent = balance_prev.saturating_sub(balance_after);
+    let cost = required_deposit
+        .total
+        .saturating_sub(required_deposit.storage_fee);
+    assert!(spent > cost);
+    assert!(spent < cost.saturating_add(NearToken::from_millinear(100)));
+
+    Ok(())
+}
+
+async fn test_finalize(
+    sandbox: &Worker<Sandbox>,
+    root_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const TIMED_SURVEY_ID: &str = "4kslq0-zn38fk-w9cm1p-7bnw0ql";
+    let fee_amount = 10u128.pow(23);
+    let reward_amount = 10u128.pow(22);
+    let participants_limit = 3u128;
+    let deposit_amount = fee_amount + (reward_amount * participants_limit);
+
+    let business_user_account_2 = root_account
+        .create_subaccount("business_user_2")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+    register_storage(&business_user_account_2, contract).await?;
+
+    let ends_at = sandbox.view_block().await?.timestamp() + 60 * 10u64.pow(9);
+    let outcome = business_user_account_2
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": TIMED_SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string(), "ends_at": ends_at}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = server_account
+        .call(contract.id(), "finalize_survey")
+        .args_json(json!({"survey_id": TIMED_SURVEY_ID}))
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Survey has not ended yet");
+
+    while sandbox.view_block().await?.timestamp() <= ends_at {
+        sandbox.fast_forward(100).await?;
+    }
+
+    let rewarded_user_account_7 = root_account
+        .create_subaccount("rewarded_user_7")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(
+            json!({"survey_id": TIMED_SURVEY_ID, "participant": rewarded_user_account_7.id()}),
+        )
+        .max_gas()
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Survey has ended");
+
+    let user_balance_prev = business_user_account_2.view_account().await?.balance;
+    let outcome = server_account
+        .call(contract.id(), "finalize_survey")
+        .args_json(json!({"survey_id": TIMED_SURVEY_ID}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let user_balance_after = business_user_account_2.view_account().await?.balance;
+
+    assert_eq!(
+        user_balance_after.as_yoctonear(),
+        user_balance_prev.as_yoctonear() + reward_amount * participants_limit
+    );
+
+    Ok(())
+}
+
+async fn test_resize(
+    root_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const RESIZED_SURVEY_ID: &str = "5mcq0a-kd03md-q0c7xn-2pqmc7s";
+    let fee_amount = 10u128.pow(23);
+    let gas_fee_per_participant = 15 * 10u128.pow(21);
+    let reward_amount = 10u128.pow(22);
+    let participants_limit = 3u128;
+    let deposit_amount = fee_amount + (reward_amount * participants_limit);
+
+    let business_user_account_3 = root_account
+        .create_subaccount("business_user_3")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+    register_storage(&business_user_account_3, contract).await?;
+
+    let outcome = business_user_account_3
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": RESIZED_SURVEY_ID, "participants_limit": participants_limit as u64, "reward_amount": reward_amount.to_string(), "gas_fee": fee_amount.to_string()}))
+        .deposit(NearToken::from_yoctonear(deposit_amount))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = business_user_account_3
+        .call(contract.id(), "top_up_survey")
+        .args_json(json!({"survey_id": RESIZED_SURVEY_ID, "additional_participants": 2}))
+        .deposit(NearToken::from_yoctonear(reward_amount * 2))
+        .transact()
+        .await?;
+    outcome
+        .into_result()
+        .expect_err("Attached deposit is not sufficient");
+
+    let outcome = business_user_account_3
+        .call(contract.id(), "top_up_survey")
+        .args_json(json!({"survey_id": RESIZED_SURVEY_ID, "additional_participants": 2}))
+        .deposit(NearToken::from_yoctonear(
+            (reward_amount + gas_fee_per_participant) * 2,
+        ))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let rewarded_user_account_8 = root_account
+        .create_subaccount("rewarded_user_8")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    // The creator rewards its participants through its own backend account.
+    let business_backend_account = root_account
+        .create_subaccount("business_backend")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let outcome = business_backend_account
+        .call(contract.id(), "reward_participant")
+        .args_json(
+            json!({"survey_id": RESIZED_SURVEY_ID, "participant": rewarded_user_account_8.id()}),
+        )
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = business_user_account_3
+        .call(contract.id(), "add_survey_rewarder")
+        .args_json(
+            json!({"survey_id": RESIZED_SURVEY_ID, "account_id": business_backend_account.id()}),
+        )
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = business_backend_account
+        .call(contract.id(), "reward_participant")
+        .args_json(
+            json!({"survey_id": RESIZED_SURVEY_ID, "participant": rewarded_user_account_8.id()}),
+        )
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Survey rewarders are limited to the survey they were appointed for.
+    let outcome = business_backend_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_8.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = business_user_account_3
+        .call(contract.id(), "shrink_survey")
+        .args_json(json!({"survey_id": RESIZED_SURVEY_ID, "participants_limit": 0}))
+        .transact()
+        .await?;
+    outcome
+        .into_result()
+        .expect_err("Participants limit cannot be lower than the rewarded participants");
+
+    let user_balance_prev = business_user_account_3.view_account().await?.balance;
+    let outcome = server_account
+        .call(contract.id(), "shrink_survey")
+        .args_json(json!({"survey_id": RESIZED_SURVEY_ID, "participants_limit": 2}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let user_balance_after = business_user_account_3.view_account().await?.balance;
+
+    assert_eq!(
+        user_balance_after.as_yoctonear(),
+        user_balance_prev.as_yoctonear() + reward_amount * 3
+    );
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": RESIZED_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_limit, 2);
+
+    Ok(())
+}
+
+async fn test_commission(
+    owner_account: &Account,
+    user_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Archived surveys may have left freed storage in the treasury.
+    let treasury_prev: NearToken = contract
+        .view("get_treasury")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+
+    let outcome = owner_account
+        .call(contract.id(), "set_commission")
+        .args_json(json!({"commission_bps": 500}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_required_deposit_outcome: RequiredDeposit = contract
+        .view("get_required_deposit")
+        .args_json(json!({"participants_limit": 2, "reward_amount": NearToken::from_near(1)}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_required_deposit_outcome.commission,
+        NearToken::from_millinear(100)
+    );
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"participants_limit": 2, "reward_amount": NearToken::from_near(1), "gas_fee": get_required_deposit_outcome.gas_fee}))
+        .deposit(get_required_deposit_outcome.total)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Without an ID the contract assigns one.
+    let survey_id: String = outcome.json()?;
+    assert_eq!(survey_id, "q-1");
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": survey_id}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_survey_outcome.commission,
+        NearToken::from_millinear(100)
+    );
+
+    let get_accounting_outcome: Accounting = contract
+        .view("get_accounting")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_accounting_outcome.treasury,
+        treasury_prev.saturating_add(NearToken::from_millinear(100))
+    );
+
+    // Only fee collectors can withdraw the treasury.
+    let outcome = user_account
+        .call(contract.id(), "withdraw_treasury")
+        .args_json(
+            json!({"amount": NearToken::from_millinear(100), "account_id": user_account.id()}),
+        )
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = owner_account
+        .call(contract.id(), "withdraw_treasury")
+        .args_json(
+            json!({"amount": NearToken::from_millinear(100), "account_id": owner_account.id()}),
+        )
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_treasury_outcome: NearToken = contract
+        .view("get_treasury")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(get_treasury_outcome, treasury_prev);
+
+    Ok(())
+}
+
+async fn test_ownership(
+    owner_account: &Account,
+    new_owner_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = owner_account
+        .call(contract.id(), "propose_owner")
+        .args_json(json!({"new_owner": new_owner_account.id()}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Only the proposed account can complete the transfer.
+    let outcome = owner_account
+        .call(contract.id(), "accept_ownership")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = new_owner_account
+        .call(contract.id(), "accept_ownership")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_owner_outcome: Option<AccountId> = contract
+        .view("get_owner")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_owner_outcome.map(|owner| owner.to_string()),
+        Some(new_owner_account.id().to_string())
+    );
+
+    let outcome = owner_account
+        .call(contract.id(), "set_gas_station")
+        .args_json(json!({"gas_station": owner_account.id()}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    // The previous owner keeps no manager permissions.
+    for role in ["SurveyManager", "Rewarder"] {
+        let has_role_outcome: bool = contract
+            .view("has_role")
+            .args_json(json!({"role": role, "account_id": owner_account.id()}))
+            .await?
+            .json()?;
+        assert!(!has_role_outcome);
+    }
+
+    let outcome = owner_account
+        .call(contract.id(), "pause_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Only the survey creator or a manager"));
+
+    Ok(())
+}
+
+#[derive(near_sdk::borsh::BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+struct QuizzlerV1 {
+    owner: AccountId,
+    gas_station: AccountId,
+    managers: Vec<u8>,
+    surveys: Vec<u8>,
+    surveys_users_rewarded: Vec<u8>,
+}
+
+#[derive(near_sdk::borsh::BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+struct SurveyV1 {
+    survey_creator: AccountId,
+    participants_limit: u64,
+    reward_amount: NearToken,
+    participants_rewarded: u64,
+    is_canceled: bool,
+}
+
+#[tokio::test]
+async fn test_migrate_v1() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = sandbox().await?;
+    let contract_wasm = near_workspaces::compile_project("./").await?;
+
+    let root = sandbox.root_account()?;
+
+    let owner_account = root
+        .create_subaccount("owner")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let contract = root
+        .create_subaccount("legacy")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap()
+        .deploy(&contract_wasm)
+        .await?
+        .unwrap();
+
+    let state = QuizzlerV1 {
+        owner: owner_account.id().clone(),
+        gas_station: root.id().clone(),
+        managers: b"m".to_vec(),
+        surveys: b"s".to_vec(),
+        surveys_users_rewarded: b"surveys_users_rewarded".to_vec(),
+    };
+    sandbox
+        .patch_state(contract.id(), b"STATE", &near_sdk::borsh::to_vec(&state)?)
+        .await?;
+
+    let survey = SurveyV1 {
+        survey_creator: owner_account.id().clone(),
+        participants_limit: 10,
+        reward_amount: NearToken::from_millinear(100),
+        participants_rewarded: 4,
+        is_canceled: false,
+    };
+    let survey_key = [b"s".to_vec(), near_sdk::borsh::to_vec(SURVEY_ID)?].concat();
+    sandbox
+        .patch_state(
+            contract.id(),
+            &survey_key,
+            &near_sdk::borsh::to_vec(&survey)?,
+        )
+        .await?;
+
+    // A participant rewarded by the v1 contract, under the raw `{survey_id}-r` prefix.
+    let legacy_prefix = format!("{}-r", SURVEY_ID).into_bytes();
+    let rewarded_key = [
+        b"surveys_users_rewarded".to_vec(),
+        near_sdk::borsh::to_vec(SURVEY_ID)?,
+    ]
+    .concat();
+    sandbox
+        .patch_state(
+            contract.id(),
+            &rewarded_key,
+            &near_sdk::borsh::to_vec(&legacy_prefix)?,
+        )
+        .await?;
+    let participant_key = [
+        legacy_prefix.clone(),
+        near_sdk::borsh::to_vec(root.id().as_str())?,
+    ]
+    .concat();
+    sandbox
+        .patch_state(
+            contract.id(),
+            &participant_key,
+            &near_sdk::borsh::to_vec(&true)?,
+        )
+        .await?;
+
+    // Only the contract itself can run the migration.
+    let outcome = owner_account
+        .call(contract.id(), "migrate")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    let outcome = contract
+        .call("migrate")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // IDs of surveys that were not moved yet stay taken.
+    let outcome = owner_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": SURVEY_ID, "participants_limit": 10u64, "reward_amount": "1", "gas_fee": "0"}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err()).contains("Survey already exists"));
+
+    let outcome = owner_account
+        .call(contract.id(), "migrate_v1_surveys")
+        .args_json(json!({"survey_ids": [SURVEY_ID]}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 4);
+    assert_eq!(get_survey_outcome.status, "Active");
+    assert_eq!(get_survey_outcome.ft_contract_id, None);
+
+    let get_accounting_outcome: Accounting = contract
+        .view("get_accounting")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_accounting_outcome.escrow_balance,
+        NearToken::from_millinear(600)
+    );
+
+    // Nothing can be withdrawn before the owner confirms that every v1 survey
+    // and its budget has been moved.
+    assert!(get_accounting_outcome.free_balance.is_zero());
+    let outcome = owner_account
+        .call(contract.id(), "emergency_withdraw")
+        .args_json(
+            json!({"amount": NearToken::from_yoctonear(1), "account_id": owner_account.id()}),
+        )
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("Not enough free balance");
+
+    let outcome = owner_account
+        .call(contract.id(), "finish_v1_migration")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_accounting_outcome: Accounting = contract
+        .view("get_accounting")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert!(!get_accounting_outcome.free_balance.is_zero());
+
+    // Rewards are charged to the storage balance of the survey creator.
+    register_storage(&owner_account, &contract).await?;
+
+    let outcome = owner_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": root.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    // The participant is still known as rewarded through the legacy prefix.
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": SURVEY_ID, "account_id": root.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    // New rewards of the survey go to the same legacy map.
+    let outcome = owner_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": owner_account.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = owner_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": owner_account.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.is_success());
+
+    // The rewards of the v1 contract left no records, but do not keep the
+    // survey from being archived.
+    let outcome = owner_account
+        .call(contract.id(), "cancel_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = owner_account
+        .call(contract.id(), "archive_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+
+    // Redeploying the same code keeps the migrated state.
+    let outcome = owner_account
+        .call(contract.id(), "update_contract")
+        .args(contract_wasm)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_survey_count_outcome: u64 = contract
+        .view("get_survey_count")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_count_outcome, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_survey() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = sandbox().await?;
+    let contract_wasm = near_workspaces::compile_project("./").await?;
+    let token_wasm = near_workspaces::compile_project("./tests/mock-ft").await?;
+
+    let root = sandbox.root_account()?;
+
+    let user_account = root
+        .create_subaccount("user")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let server_account = root
+        .create_subaccount("server")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let participant_account = root
+        .create_subaccount("participant")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let contract = root
+        .create_subaccount("quizzler")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap()
+        .deploy(&contract_wasm)
+        .await?
+        .unwrap();
+
+    let token = root
+        .create_subaccount("token")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap()
+        .deploy(&token_wasm)
+        .await?
+        .unwrap();
+
+    let outcome = server_account
+        .call(contract.id(), "new")
+        .args_json(json!({"gas_station": server_account.id()}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = user_account
+        .call(token.id(), "new")
+        .args_json(json!({"owner_id": user_account.id(), "total_supply": "1000000"}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The gas station is not registered with the token.
+    for account_id in [contract.id(), participant_account.id()] {
+        let outcome = user_account
+            .call(token.id(), "register_account")
+            .args_json(json!({"account_id": account_id}))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+    register_storage(&user_account, &contract).await?;
+
+    // Tokens that were not accepted are refunded.
+    let msg = json!({"survey_id": SURVEY_ID, "participants_limit": 2, "reward_amount": "100", "gas_fee": "10"});
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "300", "msg": msg.to_string()}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "1000000");
+
+    // Any account can call `ft_on_transfer`, which cannot create a survey on
+    // behalf of another creator.
+    let outcome = participant_account
+        .call(contract.id(), "ft_on_transfer")
+        .args_json(json!({"sender_id": user_account.id(), "amount": "300", "msg": msg.to_string()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err()).contains("Token is not accepted"));
+
+    let outcome = server_account
+        .call(contract.id(), "set_token_accepted")
+        .args_json(json!({"ft_contract_id": token.id(), "accepted": true}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_accepted_tokens_outcome: Vec<AccountId> = contract
+        .view("get_accepted_tokens")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(get_accepted_tokens_outcome, vec![token.id().clone()]);
+
+    // Without a fee config the token cannot fund surveys.
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "300", "msg": msg.to_string()}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "1000000");
+
+    let outcome = server_account
+        .call(contract.id(), "set_token_fee_config")
+        .args_json(json!({"ft_contract_id": token.id(), "fee_config": {"per_participant_fee": "4", "base_fee": "2"}}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The gas fee must cover the fee config of the token.
+    let low_fee_msg = json!({"survey_id": SURVEY_ID, "participants_limit": 2, "reward_amount": "100", "gas_fee": "9"});
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(
+            json!({"receiver_id": contract.id(), "amount": "300", "msg": low_fee_msg.to_string()}),
+        )
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "1000000");
+
+    // The survey arguments are checked before the tokens are kept.
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "1000", "msg": "invalid"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "1000000");
+
+    // 2 rewards of 100 and a gas fee of 10, the remaining 90 are returned.
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "300", "msg": msg.to_string()}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "999790");
+
+    // The gas fee the gas station cannot receive goes to the token treasury.
+    let get_treasury_outcome: U128 = contract
+        .view("get_treasury")
+        .args_json(json!({"ft_contract_id": token.id()}))
+        .await?
+        .json()?;
+    assert_eq!(get_treasury_outcome, U128(10));
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.ft_contract_id.as_ref(), Some(token.id()));
+    assert_eq!(get_survey_outcome.reward_amount.as_yoctonear(), 100);
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": participant_account.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": participant_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "100");
+
+    // The transfer to an account not registered with the token fails, and the
+    // slot is released instead of being counted as rewarded.
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": root.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome.json::<bool>()?);
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 1);
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": SURVEY_ID, "account_id": root.id()}))
+        .await?
+        .json()?;
+    assert!(!is_rewarded);
+
+    // Once registered, the same participant can be rewarded.
+    let outcome = user_account
+        .call(token.id(), "register_account")
+        .args_json(json!({"account_id": root.id()}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": SURVEY_ID, "participant": root.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": contract.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "10");
+
+    // A full batch of token transfers fits into one call.
+    const BATCH_SURVEY_ID: &str = "4hwq0d-mz81kc-pl2v7s-6trn3ay";
+    let participants = (1..=10)
+        .map(|index| format!("{:064x}", index).parse())
+        .collect::<Result<Vec<AccountId>, _>>()?;
+    for participant in &participants {
+        let outcome = user_account
+            .call(token.id(), "register_account")
+            .args_json(json!({"account_id": participant}))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    let msg = json!({"survey_id": BATCH_SURVEY_ID, "participants_limit": 10, "reward_amount": "100", "gas_fee": "42"});
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "1042", "msg": msg.to_string()}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants": participants}))
+        .max_gas()
+        .transact()
+        .await?;
+    let results: Vec<RewardResult> = outcome.json()?;
+    assert!(results.iter().all(|result| result.status == "Scheduled"));
+
+    for participant in &participants {
+        let balance: String = token
+            .view("ft_balance_of")
+            .args_json(json!({"account_id": participant}))
+            .await?
+            .json()?;
+        assert_eq!(balance, "100");
+    }
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 10);
+    assert_eq!(get_survey_outcome.status, "Completed");
+
+    // A refund the creator cannot receive is kept as a pending refund.
+    const CANCELED_SURVEY_ID: &str = "8fkd2w-q0v7nz-3jt5xb-u1ma6rc";
+    let msg = json!({"survey_id": CANCELED_SURVEY_ID, "participants_limit": 2, "reward_amount": "100", "gas_fee": "10"});
+    let outcome = user_account
+        .call(token.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "210", "msg": msg.to_string()}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = user_account
+        .call(token.id(), "unregister_account")
+        .args_json(json!({"account_id": user_account.id()}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = user_account
+        .call(contract.id(), "cancel_survey")
+        .args_json(json!({"survey_id": CANCELED_SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_pending_refund_outcome: U128 = contract
+        .view("get_pending_refund")
+        .args_json(json!({"account_id": user_account.id(), "ft_contract_id": token.id()}))
+        .await?
+        .json()?;
+    assert_eq!(get_pending_refund_outcome, U128(200));
+
+    let outcome = user_account
+        .call(token.id(), "register_account")
+        .args_json(json!({"account_id": user_account.id()}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = user_account
+        .call(contract.id(), "claim_pending_refund")
+        .args_json(json!({"ft_contract_id": token.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance, "200");
+    let get_pending_refund_outcome: U128 = contract
+        .view("get_pending_refund")
+        .args_json(json!({"account_id": user_account.id(), "ft_contract_id": token.id()}))
+        .await?
+        .json()?;
+    assert_eq!(get_pending_refund_outcome, U128(0));
+
     Ok(())
 }
@@ -0,0 +1,113 @@
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{near, AccountId, NearToken};
+
+use crate::{FeeConfig, PausableMethod, Role, RoleScope, SurveyStatus};
+
+/// NEP-297 events, logged as `EVENT_JSON:{"standard":"qstn_survey",...}`.
+#[near(event_json(standard = "qstn_survey"))]
+pub enum SurveyEvent {
+    #[event_version("1.0.0")]
+    SurveyCreated {
+        survey_id: String,
+        survey_creator: AccountId,
+        participants_limit: u64,
+        gas_fee: NearToken,
+        nft_contract_id: AccountId,
+    },
+
+    #[event_version("1.0.0")]
+    ParticipantRewarded {
+        survey_id: String,
+        participant: AccountId,
+        token_id: TokenId,
+    },
+
+    #[event_version("1.0.0")]
+    RewardFailed {
+        survey_id: String,
+        participant: AccountId,
+    },
+
+    #[event_version("1.0.0")]
+    RewardCredited {
+        survey_id: String,
+        participant: AccountId,
+        token_id: TokenId,
+    },
+
+    #[event_version("1.0.0")]
+    SurveyCanceled { survey_id: String },
+
+    #[event_version("1.0.0")]
+    SurveyStatusChanged {
+        survey_id: String,
+        status: SurveyStatus,
+    },
+
+    #[event_version("1.0.0")]
+    SurveyArchived { survey_id: String },
+
+    #[event_version("1.0.0")]
+    OwnershipProposed {
+        owner: AccountId,
+        proposed_owner: AccountId,
+    },
+
+    /// `new_owner` is `None` when the ownership was renounced.
+    #[event_version("1.0.0")]
+    OwnershipTransferred {
+        previous_owner: AccountId,
+        new_owner: Option<AccountId>,
+    },
+
+    #[event_version("1.0.0")]
+    ManagerChanged { manager: AccountId, status: bool },
+
+    #[event_version("1.0.0")]
+    RoleGranted {
+        role: Role,
+        account_id: AccountId,
+        scope: RoleScope,
+    },
+
+    #[event_version("1.0.0")]
+    RoleRevoked {
+        role: Role,
+        account_id: AccountId,
+        scope: RoleScope,
+    },
+
+    /// `method` is `None` for the contract-wide flag.
+    #[event_version("1.0.0")]
+    PauseChanged {
+        method: Option<PausableMethod>,
+        paused: bool,
+    },
+
+    #[event_version("1.0.0")]
+    GasStationChanged { gas_station: AccountId },
+
+    /// `survey_id` and `survey_creator` are `None` for the contract-wide config,
+    /// `fee_config` is `None` when a survey override was removed.
+    #[event_version("1.0.0")]
+    FeeConfigChanged {
+        survey_id: Option<String>,
+        survey_creator: Option<AccountId>,
+        fee_config: Option<FeeConfig>,
+    },
+
+    /// NEAR leaving the contract that is not a fee, such as emergency withdrawals
+    /// and deposit refunds.
+    #[event_version("1.0.0")]
+    Withdrawal {
+        account_id: AccountId,
+        amount: NearToken,
+    },
+}
+
+impl SurveyEvent {
+    /// Withdrawal of native NEAR, used by the modules shared with the native contract.
+    pub(crate) fn near_withdrawal(account_id: AccountId, amount: NearToken) -> Self {
+        SurveyEvent::Withdrawal { account_id, amount }
+    }
+}
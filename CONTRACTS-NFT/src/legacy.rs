@@ -0,0 +1,43 @@
+//! Survey layouts written by earlier versions of the contract.
+
+use near_sdk::{near, AccountId, NearToken};
+
+use crate::{Survey, SurveyStatus};
+
+/// Survey layout of the first version, which minted token IDs from
+/// `participants_rewarded`.
+#[near(serializers = [borsh])]
+#[derive(Clone)]
+pub struct SurveyV1 {
+    survey_creator: AccountId,
+    participants_limit: u64,
+    nft_contract_id: AccountId,
+    participants_rewarded: u64,
+    is_canceled: bool,
+}
+
+impl From<SurveyV1> for Survey {
+    fn from(survey: SurveyV1) -> Self {
+        Survey {
+            survey_creator: survey.survey_creator,
+            participants_limit: survey.participants_limit,
+            nft_contract_id: survey.nft_contract_id,
+            participants_rewarded: survey.participants_rewarded,
+            legacy_rewards: survey.participants_rewarded,
+            status: if survey.is_canceled {
+                SurveyStatus::Canceled
+            } else {
+                SurveyStatus::Active
+            },
+            next_token_id: survey.participants_rewarded,
+            storage_stake: NearToken::from_yoctonear(0),
+        }
+    }
+}
+
+impl SurveyV1 {
+    /// NFT surveys hold no budget, nothing of them becomes part of the escrow.
+    pub(crate) fn escrowed_budget(&self) -> NearToken {
+        NearToken::from_yoctonear(0)
+    }
+}
@@ -1,21 +1,107 @@
 use near_contract_standards::non_fungible_token::metadata::{NFTContractMetadata, TokenMetadata};
 use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_contract_standards::storage_management::StorageManagement;
 use near_sdk::serde_json;
-use near_sdk::store::LookupMap;
-use near_sdk::{env, log, near, AccountId, Gas, NearToken, PanicOnDefault, Promise, PromiseError};
+use near_sdk::store::{IterableSet, LookupMap, Vector};
+use near_sdk::{
+    env, near, AccountId, BorshStorageKey, CryptoHash, Gas, NearToken, PanicOnDefault, Promise,
+    PromiseError,
+};
+
+mod events;
+#[path = "../../shared/fees.rs"]
+mod fees;
+mod legacy;
+#[path = "../../shared/migrate.rs"]
+mod migrate;
+#[path = "../../shared/pause.rs"]
+mod pause;
+#[path = "../../shared/roles.rs"]
+mod roles;
+#[path = "../../shared/storage.rs"]
+mod storage;
+
+use events::SurveyEvent;
+use fees::{FeeConfig, SurveyFeeConfig};
+use legacy::SurveyV1;
+use pause::{PausableMethod, PauseState};
+use roles::{Role, RoleScope};
+use storage::StorageAccount;
 
 const NFT_WASM_CODE: &[u8] = include_bytes!("./nft/non_fungible_token.wasm");
 const TGAS: Gas = Gas::from_tgas(1); // 10e12yⓃ
 const NO_DEPOSIT: NearToken = NearToken::from_near(0); // 0yⓃ
+const REWARD_BATCH_LIMIT: usize = 20; // mints batched per reward_participants call
+const MINT_GAS: Gas = Gas::from_tgas(7); // mint of one reward and its share of the callback
+const BATCH_GAS_RESERVE: Gas = Gas::from_tgas(30); // reward_participants itself and the callback base
+const DEFAULT_PAGE_LIMIT: u64 = 50; // entries returned by paginated views without a limit
+const GENERATED_ID_PREFIX: &str = "q-"; // reserved for IDs assigned by create_survey
+const ARCHIVE_BATCH_LIMIT: u64 = 100; // participants deleted per archive_survey call
+const SURVEY_STORAGE_BYTES: u128 = 1_000; // upper bound of the storage deploy_callback adds
+const REWARD_STORAGE_BYTES: u64 = 1_000; // upper bound of the storage record_reward adds
+const MINT_DEPOSIT: NearToken = NearToken::from_millinear(10); // storage of one token on the survey collection
+
+/// Prefixes of the state collections. Collections of one survey, account or
+/// role scope are keyed by the hash of its ID, so no ID can reach into the keys
+/// of another collection. State written by v1 keeps its byte prefixes.
+#[near(serializers = [borsh])]
+#[derive(BorshStorageKey)]
+pub enum StorageKey {
+    SurveyFeeConfigs,
+    RoleMembers,
+    RoleMemberSet { role: Role, scope_hash: CryptoHash },
+    Surveys,
+    ArchivedSurveys,
+    SurveysByCreator,
+    CreatorSurveyList { account_hash: CryptoHash },
+    SurveysUsersRewarded,
+    SurveyRewarded { survey_hash: CryptoHash },
+    Rewards,
+    SurveyParticipants,
+    SurveyParticipantList { survey_hash: CryptoHash },
+    ParticipantSurveys,
+    ParticipantSurveyList { account_hash: CryptoHash },
+    ClaimableRewards,
+    StorageAccounts,
+    SurveyIds,
+}
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
 pub struct Quizzler {
-    owner: AccountId,
+    /// `None` once the ownership was renounced.
+    owner: Option<AccountId>,
+    proposed_owner: Option<AccountId>,
     gas_station: AccountId,
-    managers: LookupMap<AccountId, bool>,
-    surveys: LookupMap<String, Survey>,
+    fee_config: FeeConfig,
+    survey_fee_configs: LookupMap<String, SurveyFeeConfig>,
+    role_members: LookupMap<(Role, RoleScope), IterableSet<AccountId>>,
+    pause_state: PauseState,
+    /// Number used for the next contract-generated survey ID.
+    next_survey_number: u64,
+    surveys: LookupMap<String, VersionedSurvey>,
+    /// IDs of all surveys in creation order. Archived IDs are kept, so indexes
+    /// into the list do not change.
+    survey_ids: Vector<String>,
+    /// Summaries of surveys removed with `archive_survey`.
+    archived_surveys: LookupMap<String, SurveySummary>,
+    surveys_by_creator: LookupMap<AccountId, Vector<String>>,
     surveys_users_rewarded: LookupMap<String, LookupMap<AccountId, bool>>,
+    rewards: LookupMap<(String, AccountId), RewardRecord>,
+    survey_participants: LookupMap<String, Vector<AccountId>>,
+    participant_surveys: LookupMap<AccountId, Vector<String>>,
+    claimable_rewards: LookupMap<AccountId, Vec<ClaimableReward>>,
+    /// NEAR owed to creators and participants: deposits of surveys being deployed,
+    /// mint deposits of credited rewards and available storage balances.
+    escrow_balance: NearToken,
+    /// Gas fees forwarded to the gas station so far.
+    fees_forwarded: NearToken,
+    /// NEP-145 storage balances, see `storage_deposit`.
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    /// Set by `migrate` for a v1 state until `finish_v1_migration`. The budgets
+    /// of v1 surveys enter the escrow when they are moved, so there is no free
+    /// balance before.
+    v1_migration_pending: bool,
 }
 
 #[near(serializers = [json, borsh])]
@@ -25,12 +111,169 @@ pub struct Survey {
     participants_limit: u64,
     nft_contract_id: AccountId,
     participants_rewarded: u64,
-    is_canceled: bool,
+    /// Rewards paid by the first contract version, which left no reward records.
+    legacy_rewards: u64,
+    /// Stored status, `get_survey` returns the status derived with `current_status`.
+    status: SurveyStatus,
+    /// Token ID of the next mint. Credited rewards take their ID before they
+    /// are minted, so IDs cannot follow `participants_rewarded`.
+    next_token_id: u64,
+    /// Storage cost of the survey and its rewards paid by the creator.
+    storage_stake: NearToken,
+}
+
+/// Stored form of a survey. Surveys moved over from a v1 state keep their
+/// layout until they are modified and are read through `latest`. Layouts of
+/// builds between v1 and the current one were never deployed and have no
+/// variant, so such a deployment cannot be upgraded in place.
+#[near(serializers = [borsh])]
+pub enum VersionedSurvey {
+    V1(SurveyV1),
+    V2(Survey),
+}
+
+impl VersionedSurvey {
+    fn latest(&self) -> Survey {
+        match self {
+            VersionedSurvey::V1(survey) => survey.clone().into(),
+            VersionedSurvey::V2(survey) => survey.clone(),
+        }
+    }
+
+    /// Upgrades the stored survey to the current layout before handing it out.
+    fn latest_mut(&mut self) -> &mut Survey {
+        if let VersionedSurvey::V1(survey) = self {
+            *self = VersionedSurvey::V2(survey.clone().into());
+        }
+        match self {
+            VersionedSurvey::V2(survey) => survey,
+            VersionedSurvey::V1(_) => unreachable!(),
+        }
+    }
+}
+
+impl From<Survey> for VersionedSurvey {
+    fn from(survey: Survey) -> Self {
+        VersionedSurvey::V2(survey)
+    }
 }
 
+/// NFT surveys have no reward window, so there is no `Pending` or `Expired`
+/// like in the native contract. `Completed` is derived from the participants
+/// count and never stored.
 #[near(serializers = [json, borsh])]
-struct NFTInitArgs {
-    owner_id: AccountId,
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SurveyStatus {
+    Active,
+    Paused,
+    Completed,
+    Canceled,
+}
+
+impl Survey {
+    fn current_status(&self) -> SurveyStatus {
+        if self.status == SurveyStatus::Active
+            && self.participants_rewarded >= self.participants_limit
+        {
+            return SurveyStatus::Completed;
+        }
+        self.status
+    }
+
+    /// Moves the survey to `next`, panicking if the transition is not allowed
+    /// from its current status.
+    fn transition_to(&mut self, next: SurveyStatus) {
+        use SurveyStatus::*;
+
+        match (self.current_status(), next) {
+            (Active, Paused) | (Paused, Active) | (Active | Paused, Canceled) => {}
+            (Canceled, _) => panic!("Survey is canceled"),
+            (Completed, _) => panic!("Survey is finished"),
+            (current, next) => panic!(
+                "Survey status cannot change from {:?} to {:?}",
+                current, next
+            ),
+        }
+        self.status = next;
+    }
+
+    fn assert_rewardable(&self) {
+        match self.status {
+            SurveyStatus::Canceled => panic!("Survey is canceled"),
+            SurveyStatus::Paused => panic!("Survey is paused"),
+            _ => {}
+        }
+    }
+}
+
+/// Breakdown of the contract balance returned by `get_accounting`.
+#[near(serializers = [json])]
+pub struct Accounting {
+    account_balance: NearToken,
+    storage_locked: NearToken,
+    escrow_balance: NearToken,
+    fees_forwarded: NearToken,
+    /// Balance that does not back any survey and can be withdrawn by the owner.
+    free_balance: NearToken,
+}
+
+/// What is kept of a survey after `archive_survey`. `storage_stake` is the part
+/// of the creator's storage stake that was not released yet.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct SurveySummary {
+    survey_creator: AccountId,
+    participants_limit: u64,
+    participants_rewarded: u64,
+    nft_contract_id: AccountId,
+    status: SurveyStatus,
+    storage_stake: NearToken,
+    archived_at: u64,
+}
+
+/// Survey returned by the paginated views, together with its ID.
+#[near(serializers = [json])]
+pub struct SurveyEntry {
+    survey_id: String,
+    survey: Survey,
+}
+
+/// NFT minted to a participant, `rewarded_at` is the block timestamp in nanoseconds.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct RewardRecord {
+    survey_id: String,
+    participant: AccountId,
+    nft_contract_id: AccountId,
+    token_id: TokenId,
+    rewarded_at: u64,
+}
+
+/// Reward credited by a manager that the participant claims with `claim_reward`.
+/// `deposit` is the storage deposit attached to the mint when the reward is claimed.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct ClaimableReward {
+    survey_id: String,
+    nft_contract_id: AccountId,
+    token_id: TokenId,
+    token_metadata: TokenMetadata,
+    deposit: NearToken,
+}
+
+#[near(serializers = [json])]
+pub enum RewardStatus {
+    Scheduled,
+    AlreadyRewarded,
+    LimitReached,
+    /// Left out of the bounded chunk, the account should be sent again.
+    NotProcessed,
+}
+
+#[near(serializers = [json])]
+pub struct RewardResult {
+    participant: AccountId,
+    status: RewardStatus,
 }
 
 #[near(serializers = [json, borsh])]
@@ -43,6 +286,8 @@ pub struct TokenArgs {
 pub struct MintRequiredArgs {
     gas_fee: NearToken,
     mint_fee: NearToken,
+    /// Upper bound of the survey storage, including a storage registration.
+    storage_fee: NearToken,
     common_fee: NearToken,
 }
 
@@ -59,74 +304,65 @@ impl Quizzler {
     pub fn new(gas_station: AccountId) -> Self {
         assert!(!env::state_exists(), "The contract is already initialized");
 
-        let predecessor = env::predecessor_account_id();
-        let mut managers = LookupMap::new(b"m");
-        managers.insert(predecessor.clone(), true);
-
-        Self {
-            owner: predecessor,
-            gas_station,
-            managers: managers,
-            surveys: LookupMap::new(b"s"),
-            surveys_users_rewarded: LookupMap::new(b"surveys_users_rewarded".to_vec()),
-        }
-    }
-
-    pub fn set_manager(&mut self, manager: AccountId, status: bool) {
-        self.assert_owner();
-        self.managers.insert(manager, status);
-    }
-
-    pub fn is_manager(&self, manager: AccountId) -> bool {
-        self.managers.get(&manager).unwrap_or(&false).clone()
+        Self::initial_state(env::predecessor_account_id(), gas_station)
     }
 
     pub fn set_gas_station(&mut self, gas_station: AccountId) {
-        self.assert_owner();
-        self.gas_station = gas_station;
+        self.assert_admin();
+        self.gas_station = gas_station.clone();
+
+        SurveyEvent::GasStationChanged { gas_station }.emit();
     }
 
     pub fn get_gas_station(&self) -> AccountId {
         self.gas_station.clone()
     }
 
-    pub fn get_required_deposit(&self, participants_limit: u64) -> MintRequiredArgs {
+    /// Returns the deposit `create_survey` expects, pass `survey_id` and
+    /// `survey_creator` to apply a fee override.
+    pub fn get_required_deposit(
+        &self,
+        participants_limit: u64,
+        survey_id: Option<String>,
+        survey_creator: Option<AccountId>,
+    ) -> MintRequiredArgs {
         assert!(
             participants_limit > 0,
             "Participants limit must be greater than 0"
         );
 
-        let fee_needed = NearToken::from_yoctonear(15 * 10u128.pow(21))
-            .saturating_mul(participants_limit.clone() as u128);
+        let fee_needed = self
+            .fee_config_for(survey_id.as_deref(), survey_creator.as_ref())
+            .gas_fee(participants_limit);
 
-        let code = NFT_WASM_CODE.to_vec();
-        let minimum_needed = NearToken::from_yoctonear(
-            env::storage_byte_cost()
-                .saturating_mul(code.len() as u128)
-                .as_yoctonear()
-                + 5 * 10u128.pow(23),
-        );
+        let minimum_needed = Self::deploy_fee();
+        let storage_fee = self.survey_storage_fee();
 
-        let required_deposit =
-            NearToken::from_yoctonear(fee_needed.as_yoctonear() + minimum_needed.as_yoctonear());
+        let required_deposit = fee_needed
+            .saturating_add(minimum_needed)
+            .saturating_add(storage_fee);
 
-        let required_data = MintRequiredArgs {
+        MintRequiredArgs {
             gas_fee: fee_needed,
             mint_fee: minimum_needed,
+            storage_fee,
             common_fee: required_deposit,
-        };
-
-        return required_data;
+        }
     }
 
+    /// Deploys the reward collection of a new survey. The promise resolves to
+    /// the survey ID, which the contract assigns when `survey_id` is `None`.
     #[payable]
     pub fn create_survey(
         &mut self,
-        survey_id: String,
+        survey_id: Option<String>,
         participants_limit: u64,
         gas_fee: NearToken,
         metadata: NFTContractMetadata,
     ) -> Promise {
+        self.assert_not_paused(Some(PausableMethod::Create));
+        let survey_id = self.claim_survey_id(survey_id);
+
         assert!(
             participants_limit > 0,
             "Participants limit must be greater than 0"
@@ -135,6 +371,16 @@ impl Quizzler {
         let attached_deposit = env::attached_deposit();
         let current_account = env::current_account_id().to_string();
 
+        // The collection lives at `{survey_id}.{current_account}`, checked before
+        // any funds move.
+        let new_nft_contract_account_id = format!("{survey_id}.{current_account}");
+        assert!(
+            env::is_valid_account_id(new_nft_contract_account_id.as_bytes()),
+            "Survey ID is too long for a subaccount of {}",
+            current_account
+        );
+        let new_nft_contract_account_id: AccountId = new_nft_contract_account_id.parse().unwrap();
+
         metadata.assert_valid();
 
         let args = TokenArgs {
@@ -142,25 +388,21 @@ impl Quizzler {
             metadata,
         };
 
-        let fee_needed = NearToken::from_yoctonear(15 * 10u128.pow(21))
-            .saturating_mul(participants_limit.clone() as u128);
-        assert!(
-            gas_fee >= fee_needed,
-            "Gas fee is not sufficient. Required: {}, Attached: {}",
-            fee_needed,
-            gas_fee
+        self.assert_gas_fee(
+            &survey_id,
+            &env::predecessor_account_id(),
+            participants_limit,
+            gas_fee,
         );
 
         let code = NFT_WASM_CODE.to_vec();
-        let minimum_needed = NearToken::from_yoctonear(
-            env::storage_byte_cost()
-                .saturating_mul(code.len() as u128)
-                .as_yoctonear()
-                + 5 * 10u128.pow(23),
-        );
+        let minimum_needed = Self::deploy_fee();
 
-        let required_deposit =
-            NearToken::from_yoctonear(gas_fee.as_yoctonear() + minimum_needed.as_yoctonear());
+        // The storage is charged in `deploy_callback`, so its upper bound is
+        // required up front and the unused part is refunded there.
+        let required_deposit = gas_fee
+            .saturating_add(minimum_needed)
+            .saturating_add(self.survey_storage_fee());
 
         assert!(
             attached_deposit >= required_deposit,
@@ -169,15 +411,9 @@ impl Quizzler {
             attached_deposit
         );
 
-        let new_nft_contract_account_id: AccountId =
-            format!("{survey_id}.{current_account}").parse().unwrap();
+        // Held until `deploy_callback` has paid out the deposit.
+        self.escrow_balance = self.escrow_balance.saturating_add(attached_deposit);
 
-        assert!(
-            env::is_valid_account_id(new_nft_contract_account_id.as_bytes()),
-            "Invalid subaccount"
-        );
-
-        log!("Creating new NFT contract: {}", minimum_needed.clone());
         Promise::new(new_nft_contract_account_id.clone())
             .create_account()
             .transfer(minimum_needed)
@@ -205,80 +441,400 @@ impl Quizzler {
         participant: AccountId,
         metadata: TokenMetadata,
     ) -> Promise {
-        self.assert_manager();
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        self.assert_survey_role(&survey_id, Role::Rewarder);
 
         let attached_deposit = env::attached_deposit();
+        Self::assert_mint_deposit(attached_deposit);
+
+        // The slot is reserved until `mint_callback` resolves the mint.
+        let (nft_contract_id, token_id) = self.reserve_reward(&survey_id, &participant);
+
+        Self::mint(
+            Promise::new(nft_contract_id),
+            token_id.clone(),
+            participant.clone(),
+            metadata,
+            attached_deposit,
+        )
+        .then(Self::ext(env::current_account_id()).mint_callback(
+            survey_id,
+            token_id,
+            participant,
+            attached_deposit,
+        ))
+    }
+
+    /// Rewards up to `REWARD_BATCH_LIMIT` participants with a single batch of mints,
+    /// fewer if the attached gas does not cover them. The attached deposit is
+    /// split evenly between them, each share covering `MINT_DEPOSIT`, and the
+    /// rest is returned. Accounts that were
+    /// already rewarded or did not fit into the limits are skipped and reported.
+    #[payable]
+    pub fn reward_participants(
+        &mut self,
+        survey_id: String,
+        participants: Vec<AccountId>,
+        metadata: TokenMetadata,
+    ) -> Vec<RewardResult> {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        self.assert_survey_role(&survey_id, Role::Rewarder);
+
+        let attached_deposit = env::attached_deposit();
+        let batch_limit = Self::reward_batch_limit();
 
         let survey = self
             .surveys
-            .get_mut(&survey_id)
-            .expect("Survey does not exist");
-        assert!(!survey.is_canceled, "Survey is canceled");
+            .get(&survey_id)
+            .expect("Survey does not exist")
+            .latest();
+        survey.assert_rewardable();
+        let rewarded = self.surveys_users_rewarded.get(&survey_id);
 
-        let prefix = format!("{}-r", survey_id.clone()).into_bytes();
-        let rewarded = self
-            .surveys_users_rewarded
-            .entry(survey_id.clone())
-            .or_insert_with(|| LookupMap::new(prefix));
+        let mut scheduled: Vec<AccountId> = Vec::new();
+        let results = participants
+            .into_iter()
+            .map(|participant| {
+                let status = if rewarded.is_some_and(|rewarded| rewarded.contains_key(&participant))
+                    || scheduled.contains(&participant)
+                {
+                    RewardStatus::AlreadyRewarded
+                } else if survey.participants_rewarded + scheduled.len() as u64
+                    >= survey.participants_limit
+                {
+                    RewardStatus::LimitReached
+                } else if scheduled.len() >= batch_limit {
+                    RewardStatus::NotProcessed
+                } else {
+                    scheduled.push(participant.clone());
+                    RewardStatus::Scheduled
+                };
+                RewardResult {
+                    participant,
+                    status,
+                }
+            })
+            .collect();
 
-        if !rewarded.contains_key(&participant) {
-            assert!(
-                survey.participants_rewarded < survey.participants_limit,
-                "Participant limit reached"
-            );
+        if scheduled.is_empty() {
+            if !attached_deposit.is_zero() {
+                Promise::new(env::predecessor_account_id()).transfer(attached_deposit);
 
-            let args = MintArgs {
-                token_id: TokenId::from(survey.participants_rewarded.to_string()),
-                receiver_id: participant.clone(),
-                token_metadata: metadata,
-            };
+                SurveyEvent::Withdrawal {
+                    account_id: env::predecessor_account_id(),
+                    amount: attached_deposit,
+                }
+                .emit();
+            }
+            return results;
+        }
 
-            Promise::new(survey.nft_contract_id.clone())
-                .function_call(
-                    "nft_mint".to_owned(),
-                    serde_json::to_vec(&args).unwrap(),
-                    attached_deposit.clone(),
-                    TGAS.saturating_mul(5),
-                )
-                .then(Self::ext(env::current_account_id()).mint_callback(
-                    survey_id,
-                    TokenId::from(survey.participants_rewarded.to_string()),
+        // The slots are reserved until `batch_mint_callback` resolves the mints.
+        let first_token_id = survey.next_token_id;
+        for participant in &scheduled {
+            self.reserve_reward(&survey_id, participant);
+        }
+
+        let deposit_per_mint = attached_deposit.saturating_div(scheduled.len() as u128);
+        Self::assert_mint_deposit(deposit_per_mint);
+        let minted = deposit_per_mint.saturating_mul(scheduled.len() as u128);
+        let remainder = attached_deposit.saturating_sub(minted);
+        if !remainder.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(remainder);
+
+            SurveyEvent::Withdrawal {
+                account_id: env::predecessor_account_id(),
+                amount: remainder,
+            }
+            .emit();
+        }
+
+        let mints = scheduled.iter().enumerate().fold(
+            Promise::new(survey.nft_contract_id.clone()),
+            |promise, (index, participant)| {
+                Self::mint(
+                    promise,
+                    TokenId::from((first_token_id + index as u64).to_string()),
                     participant.clone(),
-                    attached_deposit,
-                ))
-        } else {
-            panic!("Participant already rewarded");
+                    metadata.clone(),
+                    deposit_per_mint,
+                )
+            },
+        );
+
+        let callback_gas = TGAS.saturating_mul(5 + 2 * scheduled.len() as u64);
+        mints.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(callback_gas)
+                .batch_mint_callback(survey_id, scheduled, first_token_id, minted),
+        );
+
+        results
+    }
+
+    #[payable]
+    pub fn credit_reward(
+        &mut self,
+        survey_id: String,
+        participant: AccountId,
+        metadata: TokenMetadata,
+    ) {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        self.assert_survey_role(&survey_id, Role::Rewarder);
+        Self::assert_mint_deposit(env::attached_deposit());
+
+        // The slot is reserved now, the token is minted when the participant claims it.
+        let (nft_contract_id, token_id) = self.reserve_reward(&survey_id, &participant);
+
+        let mut claimable = self
+            .claimable_rewards
+            .get(&participant)
+            .cloned()
+            .unwrap_or_default();
+        claimable.push(ClaimableReward {
+            survey_id: survey_id.clone(),
+            nft_contract_id,
+            token_id: token_id.clone(),
+            token_metadata: metadata,
+            deposit: env::attached_deposit(),
+        });
+        self.escrow_balance = self.escrow_balance.saturating_add(env::attached_deposit());
+        self.claimable_rewards
+            .insert(participant.clone(), claimable);
+
+        SurveyEvent::RewardCredited {
+            survey_id,
+            participant,
+            token_id,
+        }
+        .emit();
+    }
+
+    pub fn claim_reward(&mut self, survey_id: String) -> Promise {
+        self.assert_not_paused(Some(PausableMethod::Reward));
+
+        let participant = env::predecessor_account_id();
+
+        let claimable = self
+            .claimable_rewards
+            .get_mut(&participant)
+            .expect("No claimable reward");
+        let index = claimable
+            .iter()
+            .position(|reward| reward.survey_id == survey_id)
+            .expect("No claimable reward");
+        let reward = claimable.swap_remove(index);
+        if claimable.is_empty() {
+            self.claimable_rewards.remove(&participant);
         }
+
+        Self::mint(
+            Promise::new(reward.nft_contract_id.clone()),
+            reward.token_id.clone(),
+            participant.clone(),
+            reward.token_metadata.clone(),
+            reward.deposit,
+        )
+        .then(Self::ext(env::current_account_id()).claim_callback(participant, reward))
+    }
+
+    pub fn get_claimable(&self, account_id: AccountId) -> Vec<ClaimableReward> {
+        self.claimable_rewards
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Participants rewarded by the first contract version have no reward record
+    /// and are only found in the rewarded flags of the survey.
+    pub fn is_rewarded(&self, survey_id: String, account_id: AccountId) -> bool {
+        self.surveys_users_rewarded
+            .get(&survey_id)
+            .is_some_and(|rewarded| rewarded.get(&account_id) == Some(&true))
+            || self.rewards.contains_key(&(survey_id, account_id))
+    }
+
+    /// Lists the NFTs minted in a survey, in the order they were minted.
+    pub fn get_survey_participants(
+        &self,
+        survey_id: String,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<RewardRecord> {
+        let Some(participants) = self.survey_participants.get(&survey_id) else {
+            return Vec::new();
+        };
+
+        participants
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|participant| {
+                self.rewards
+                    .get(&(survey_id.clone(), participant.clone()))
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// Lists the NFTs an account received, in the order they were minted.
+    pub fn get_participant_surveys(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<RewardRecord> {
+        let Some(survey_ids) = self.participant_surveys.get(&account_id) else {
+            return Vec::new();
+        };
+
+        survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.rewards
+                    .get(&(survey_id.clone(), account_id.clone()))
+                    .cloned()
+            })
+            .collect()
     }
 
     pub fn cancel_survey(&mut self, survey_id: String) {
+        self.assert_not_paused(Some(PausableMethod::Cancel));
+
         let survey_creator = {
-            let survey = self.surveys.get(&survey_id).expect("Survey does not exist");
+            let survey = self
+                .surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .latest();
             survey.survey_creator.clone()
         };
-        self.creator_or_manager(&survey_creator);
+        self.assert_creator_or_role(&survey_id, &survey_creator, &[Role::SurveyManager]);
 
         let survey = self
             .surveys
             .get_mut(&survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+
+        survey.transition_to(SurveyStatus::Canceled);
+
+        SurveyEvent::SurveyCanceled { survey_id }.emit();
+    }
+
+    pub fn pause_survey(&mut self, survey_id: String) {
+        self.set_survey_status(survey_id, SurveyStatus::Paused);
+    }
+
+    pub fn resume_survey(&mut self, survey_id: String) {
+        self.assert_not_paused(None);
+
+        self.set_survey_status(survey_id, SurveyStatus::Active);
+    }
+
+    /// Removes a canceled or completed survey whose rewards are all settled and
+    /// keeps a `SurveySummary` of it. Its participant list is deleted in chunks,
+    /// so large surveys take several calls; returns `true` once done. The reward
+    /// records stay. The freed storage goes back to the creator's storage balance
+    /// up to their stake.
+    pub fn archive_survey(&mut self, survey_id: String) -> bool {
+        self.assert_not_paused(None);
+        let initial_storage = env::storage_usage();
+
+        let survey_creator = match self.surveys.get(&survey_id) {
+            Some(survey) => survey.latest().survey_creator,
+            None => self
+                .archived_surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .survey_creator
+                .clone(),
+        };
+        self.assert_creator_or_role(&survey_id, &survey_creator, &[Role::SurveyManager]);
+
+        if self.surveys.contains_key(&survey_id) {
+            self.begin_archive(&survey_id);
+        } else if !self.survey_participants.contains_key(&survey_id) {
+            return true;
+        }
+
+        let mut archived = true;
+        if let Some(participants) = self.survey_participants.get_mut(&survey_id) {
+            for _ in 0..ARCHIVE_BATCH_LIMIT {
+                let Some(participant) = participants.pop() else {
+                    break;
+                };
+                if let Some(rewarded) = self.surveys_users_rewarded.get_mut(&survey_id) {
+                    rewarded.remove(&participant);
+                }
+            }
+            archived = participants.is_empty();
+        }
+        self.flush_survey_storage(&survey_id, &survey_creator);
+        if archived {
+            self.survey_participants.remove(&survey_id);
+            self.surveys_users_rewarded.remove(&survey_id);
+        }
+        self.surveys.flush();
+        self.archived_surveys.flush();
+        self.survey_participants.flush();
+        self.surveys_users_rewarded.flush();
+        self.survey_fee_configs.flush();
+
+        // Storage the contract paid for is simply freed.
+        let freed = Self::storage_freed_since(initial_storage);
+        let summary = self
+            .archived_surveys
+            .get_mut(&survey_id)
             .expect("Survey does not exist");
+        let stake = freed.min(summary.storage_stake);
+        summary.storage_stake = summary.storage_stake.saturating_sub(stake);
+        self.release_storage(&survey_creator, stake);
 
-        assert!(!survey.is_canceled, "Survey is canceled");
-        assert!(
-            survey.participants_limit > survey.participants_rewarded,
-            "Survey is finished"
-        );
+        if archived {
+            SurveyEvent::SurveyArchived { survey_id }.emit();
+        }
+        archived
+    }
 
-        survey.is_canceled = true;
+    pub fn get_accounting(&self) -> Accounting {
+        let account_balance = env::account_balance();
+        let storage_locked = env::storage_byte_cost().saturating_mul(env::storage_usage() as u128);
+        Accounting {
+            account_balance,
+            storage_locked,
+            escrow_balance: self.escrow_balance,
+            fees_forwarded: self.fees_forwarded,
+            free_balance: if self.v1_migration_pending {
+                NearToken::from_yoctonear(0)
+            } else {
+                account_balance
+                    .saturating_sub(storage_locked)
+                    .saturating_sub(self.escrow_balance)
+            },
+        }
     }
 
+    /// Withdraws NEAR that does not back any survey, see `get_accounting`.
     pub fn emergency_withdraw(&mut self, amount: NearToken, account_id: AccountId) {
-        self.assert_owner();
-        assert!(env::account_balance() >= amount, "Not enough balance");
-        Promise::new(account_id).transfer(amount);
+        self.assert_global_role(Role::FeeCollector);
+        assert!(
+            amount <= self.get_accounting().free_balance,
+            "Not enough free balance"
+        );
+        Promise::new(account_id.clone()).transfer(amount);
+
+        SurveyEvent::Withdrawal { account_id, amount }.emit();
     }
 
+    /// Returns the survey ID, or `None` when the collection could not be deployed.
     #[private]
+    #[allow(clippy::too_many_arguments)]
     pub fn deploy_callback(
         &mut self,
         survey_id: String,
@@ -288,36 +844,67 @@ impl Quizzler {
         gas_fee: NearToken,
         attached: NearToken,
         #[callback_result] create_deploy_result: Result<(), PromiseError>,
-    ) -> bool {
+    ) -> Option<String> {
+        self.escrow_balance = self.escrow_balance.saturating_sub(attached);
+
         if let Ok(_result) = create_deploy_result {
+            let initial_storage = env::storage_usage();
             let survey = Survey {
                 survey_creator: user.clone(),
                 participants_limit,
                 nft_contract_id: contract_id.clone(),
                 participants_rewarded: 0,
-                is_canceled: false,
+                legacy_rewards: 0,
+                status: SurveyStatus::Active,
+                next_token_id: 0,
+                storage_stake: NearToken::from_yoctonear(0),
             };
-            self.surveys.insert(survey_id.clone(), survey);
+            self.insert_survey(survey_id.clone(), survey.into());
 
+            // `create_survey` required the storage upper bound, so the surplus
+            // covers whatever the creator's storage balance does not.
+            let surplus = attached
+                .saturating_sub(gas_fee)
+                .saturating_sub(Self::deploy_fee());
+            let storage_deposit =
+                self.charge_survey_storage(&survey_id, initial_storage, 0, surplus);
+
+            self.fees_forwarded = self.fees_forwarded.saturating_add(gas_fee);
             Promise::new(self.gas_station.clone()).transfer(gas_fee);
 
-            log!("Correctly created and deployed to {}", contract_id);
-            log!("survey_id: {}", survey_id);
-            log!("participants_limit: {}", participants_limit);
-            log!("gas_fee: {}", gas_fee);
-            log!("survey_creator: {}", user);
+            SurveyEvent::SurveyCreated {
+                survey_id: survey_id.clone(),
+                survey_creator: user.clone(),
+                participants_limit,
+                gas_fee,
+                nft_contract_id: contract_id,
+            }
+            .emit();
+
+            // Whatever was attached on top of the deploy, gas and storage fees goes
+            // back to the creator.
+            let surplus = surplus.saturating_sub(storage_deposit);
+            if !surplus.is_zero() {
+                Promise::new(user.clone()).transfer(surplus);
 
-            return true;
+                SurveyEvent::Withdrawal {
+                    account_id: user,
+                    amount: surplus,
+                }
+                .emit();
+            }
+
+            return Some(survey_id);
         };
 
-        log!(
-            "Error creating {}, returning {}yⓃ to {}",
-            contract_id,
-            attached,
-            user
-        );
-        Promise::new(user).transfer(attached);
-        false
+        Promise::new(user.clone()).transfer(attached);
+
+        SurveyEvent::Withdrawal {
+            account_id: user,
+            amount: attached,
+        }
+        .emit();
+        None
     }
 
     #[private]
@@ -330,68 +917,567 @@ impl Quizzler {
         #[callback_result] mint_result: Result<Token, PromiseError>,
     ) -> bool {
         if let Ok(_result) = mint_result {
-            let survey = self
-                .surveys
-                .get_mut(&survey_id)
-                .expect("Survey does not exist");
+            self.settle_reward(&survey_id, &participant, &token_id);
 
-            let prefix = format!("{}-r", survey_id.clone()).into_bytes();
-            let rewarded = self
-                .surveys_users_rewarded
-                .entry(survey_id.clone())
-                .or_insert_with(|| LookupMap::new(prefix));
+            SurveyEvent::ParticipantRewarded {
+                survey_id,
+                participant,
+                token_id,
+            }
+            .emit();
+            return true;
+        };
 
-            survey.participants_rewarded += 1;
-            rewarded.insert(participant.clone(), true);
+        self.release_reward(&survey_id, &participant);
+        Promise::new(self.gas_station.clone()).transfer(attached);
+
+        SurveyEvent::RewardFailed {
+            survey_id,
+            participant,
+        }
+        .emit();
+        false
+    }
 
-            log!("Minting successful");
-            log!("survey_id: {}", survey_id);
-            log!("participant: {}", participant);
-            log!("token_id: {}", token_id);
+    /// The mints of `reward_participants` run as actions of one receipt, so they
+    /// either all succeed or all fail.
+    #[private]
+    pub fn batch_mint_callback(
+        &mut self,
+        survey_id: String,
+        participants: Vec<AccountId>,
+        first_token_id: u64,
+        attached: NearToken,
+        #[callback_result] mint_result: Result<Token, PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = mint_result {
+            for (index, participant) in participants.into_iter().enumerate() {
+                let token_id = TokenId::from((first_token_id + index as u64).to_string());
+                self.settle_reward(&survey_id, &participant, &token_id);
 
+                SurveyEvent::ParticipantRewarded {
+                    survey_id: survey_id.clone(),
+                    participant,
+                    token_id,
+                }
+                .emit();
+            }
             return true;
         };
 
-        log!("Minting error");
-        log!("survey_id: {}", survey_id);
-        log!("participant: {}", participant);
         Promise::new(self.gas_station.clone()).transfer(attached);
+
+        for participant in participants {
+            self.release_reward(&survey_id, &participant);
+            SurveyEvent::RewardFailed {
+                survey_id: survey_id.clone(),
+                participant,
+            }
+            .emit();
+        }
+        false
+    }
+
+    #[private]
+    pub fn claim_callback(
+        &mut self,
+        participant: AccountId,
+        reward: ClaimableReward,
+        #[callback_result] mint_result: Result<Token, PromiseError>,
+    ) -> bool {
+        if let Ok(_result) = mint_result {
+            if let Some(rewarded) = self.surveys_users_rewarded.get_mut(&reward.survey_id) {
+                rewarded.insert(participant.clone(), true);
+            }
+            self.record_reward(
+                &reward.survey_id,
+                &participant,
+                reward.nft_contract_id.clone(),
+                &reward.token_id,
+            );
+            self.escrow_balance = self.escrow_balance.saturating_sub(reward.deposit);
+
+            SurveyEvent::ParticipantRewarded {
+                survey_id: reward.survey_id,
+                participant,
+                token_id: reward.token_id,
+            }
+            .emit();
+            return true;
+        };
+
+        SurveyEvent::RewardFailed {
+            survey_id: reward.survey_id.clone(),
+            participant: participant.clone(),
+        }
+        .emit();
+
+        let mut claimable = self
+            .claimable_rewards
+            .get(&participant)
+            .cloned()
+            .unwrap_or_default();
+        claimable.push(reward);
+        self.claimable_rewards.insert(participant, claimable);
         false
     }
 
     pub fn get_survey(&self, survey_id: String) -> Survey {
-        self.surveys
+        let mut survey = self
+            .surveys
             .get(&survey_id)
             .expect("Survey does not exist")
-            .clone()
+            .latest();
+        survey.status = survey.current_status();
+        survey
+    }
+
+    /// Number of surveys created, archived ones included.
+    pub fn get_survey_count(&self) -> u64 {
+        self.survey_ids.len() as u64
+    }
+
+    /// Lists surveys in creation order. `from_index` is an index into all created
+    /// surveys and archived ones are skipped, so a page can hold fewer than `limit`
+    /// entries.
+    pub fn get_surveys(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<SurveyEntry> {
+        self.survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.surveys
+                    .get(survey_id)
+                    .map(|survey| Self::survey_entry(survey_id, survey))
+            })
+            .collect()
+    }
+
+    pub fn get_surveys_by_creator(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<SurveyEntry> {
+        let Some(survey_ids) = self.surveys_by_creator.get(&account_id) else {
+            return Vec::new();
+        };
+
+        survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.surveys
+                    .get(survey_id)
+                    .map(|survey| Self::survey_entry(survey_id, survey))
+            })
+            .collect()
+    }
+
+    /// Lists the surveys that currently accept rewards. `from_index` is an index
+    /// into all created surveys, so a page can hold fewer than `limit` entries.
+    pub fn get_active_surveys(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<SurveyEntry> {
+        self.survey_ids
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .filter_map(|survey_id| {
+                self.surveys
+                    .get(survey_id)
+                    .filter(|survey| survey.latest().current_status() == SurveyStatus::Active)
+                    .map(|survey| Self::survey_entry(survey_id, survey))
+            })
+            .collect()
+    }
+
+    pub fn get_archived_survey(&self, survey_id: String) -> Option<SurveySummary> {
+        self.archived_surveys.get(&survey_id).cloned()
+    }
+
+    /// Fresh state owned by `owner`, who passes every role check as an admin.
+    fn initial_state(owner: AccountId, gas_station: AccountId) -> Self {
+        Self {
+            owner: Some(owner),
+            proposed_owner: None,
+            gas_station,
+            fee_config: FeeConfig::default(),
+            survey_fee_configs: LookupMap::new(StorageKey::SurveyFeeConfigs),
+            role_members: LookupMap::new(StorageKey::RoleMembers),
+            pause_state: PauseState::default(),
+            next_survey_number: 1,
+            surveys: LookupMap::new(StorageKey::Surveys),
+            survey_ids: Vector::new(StorageKey::SurveyIds),
+            archived_surveys: LookupMap::new(StorageKey::ArchivedSurveys),
+            surveys_by_creator: LookupMap::new(StorageKey::SurveysByCreator),
+            surveys_users_rewarded: LookupMap::new(StorageKey::SurveysUsersRewarded),
+            rewards: LookupMap::new(StorageKey::Rewards),
+            survey_participants: LookupMap::new(StorageKey::SurveyParticipants),
+            participant_surveys: LookupMap::new(StorageKey::ParticipantSurveys),
+            claimable_rewards: LookupMap::new(StorageKey::ClaimableRewards),
+            escrow_balance: NearToken::from_yoctonear(0),
+            fees_forwarded: NearToken::from_yoctonear(0),
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            v1_migration_pending: false,
+        }
+    }
+
+    fn insert_survey(&mut self, survey_id: String, survey: VersionedSurvey) {
+        let survey_creator = survey.latest().survey_creator;
+        let prefix = StorageKey::CreatorSurveyList {
+            account_hash: env::sha256_array(survey_creator.as_bytes()),
+        };
+        self.surveys_by_creator
+            .entry(survey_creator)
+            .or_insert_with(|| Vector::new(prefix))
+            .push(survey_id.clone());
+        self.survey_ids.push(survey_id.clone());
+        self.surveys.insert(survey_id, survey);
+    }
+
+    /// Replaces the survey with its summary, once nothing can change it anymore.
+    fn begin_archive(&mut self, survey_id: &str) {
+        let survey = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest();
+        let status = survey.current_status();
+        assert!(
+            matches!(status, SurveyStatus::Canceled | SurveyStatus::Completed),
+            "Only canceled or completed surveys can be archived"
+        );
+
+        // Credited rewards are counted but not recorded until they are claimed.
+        let settled = self
+            .survey_participants
+            .get(survey_id)
+            .map_or(0, |participants| participants.len() as u64);
+        assert!(
+            survey.participants_rewarded == settled + survey.legacy_rewards,
+            "Survey has rewards that are not settled"
+        );
+
+        self.surveys.remove(survey_id);
+        self.survey_fee_configs.remove(survey_id);
+        self.archived_surveys.insert(
+            survey_id.to_string(),
+            SurveySummary {
+                survey_creator: survey.survey_creator,
+                participants_limit: survey.participants_limit,
+                participants_rewarded: survey.participants_rewarded,
+                nft_contract_id: survey.nft_contract_id,
+                status,
+                storage_stake: survey.storage_stake,
+                archived_at: env::block_timestamp(),
+            },
+        );
+    }
+
+    fn survey_entry(survey_id: &str, survey: &VersionedSurvey) -> SurveyEntry {
+        let mut survey = survey.latest();
+        survey.status = survey.current_status();
+        SurveyEntry {
+            survey_id: survey_id.to_string(),
+            survey,
+        }
+    }
+
+    fn record_reward(
+        &mut self,
+        survey_id: &String,
+        participant: &AccountId,
+        nft_contract_id: AccountId,
+        token_id: &TokenId,
+    ) {
+        self.rewards.insert(
+            (survey_id.clone(), participant.clone()),
+            RewardRecord {
+                survey_id: survey_id.clone(),
+                participant: participant.clone(),
+                nft_contract_id,
+                token_id: token_id.clone(),
+                rewarded_at: env::block_timestamp(),
+            },
+        );
+
+        let prefix = StorageKey::SurveyParticipantList {
+            survey_hash: env::sha256_array(survey_id.as_bytes()),
+        };
+        self.survey_participants
+            .entry(survey_id.clone())
+            .or_insert_with(|| Vector::new(prefix))
+            .push(participant.clone());
+
+        let prefix = StorageKey::ParticipantSurveyList {
+            account_hash: env::sha256_array(participant.as_bytes()),
+        };
+        self.participant_surveys
+            .entry(participant.clone())
+            .or_insert_with(|| Vector::new(prefix))
+            .push(survey_id.clone());
+    }
+
+    /// Takes a slot and the next token ID of the survey for `participant`, who
+    /// counts as rewarded until the mint fails. The storage of the reward is
+    /// charged to the survey creator.
+    fn reserve_reward(
+        &mut self,
+        survey_id: &String,
+        participant: &AccountId,
+    ) -> (AccountId, TokenId) {
+        let initial_storage = env::storage_usage();
+
+        let survey = self
+            .surveys
+            .get_mut(survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.assert_rewardable();
+
+        let prefix = StorageKey::SurveyRewarded {
+            survey_hash: env::sha256_array(survey_id.as_bytes()),
+        };
+        let rewarded = self
+            .surveys_users_rewarded
+            .entry(survey_id.clone())
+            .or_insert_with(|| LookupMap::new(prefix));
+
+        assert!(
+            !rewarded.contains_key(participant),
+            "Participant already rewarded"
+        );
+        assert!(
+            survey.participants_rewarded < survey.participants_limit,
+            "Participant limit reached"
+        );
+
+        let token_id = TokenId::from(survey.next_token_id.to_string());
+        survey.next_token_id += 1;
+        survey.participants_rewarded += 1;
+        rewarded.insert(participant.clone(), false);
+        let nft_contract_id = survey.nft_contract_id.clone();
+
+        // The reward record is written once the mint resolved, its storage is
+        // prepaid here.
+        self.charge_survey_storage(
+            survey_id,
+            initial_storage,
+            REWARD_STORAGE_BYTES,
+            NearToken::from_yoctonear(0),
+        );
+        (nft_contract_id, token_id)
+    }
+
+    /// Records the minted token of a reward taken with `reserve_reward`.
+    fn settle_reward(&mut self, survey_id: &String, participant: &AccountId, token_id: &TokenId) {
+        let nft_contract_id = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest()
+            .nft_contract_id;
+        if let Some(rewarded) = self.surveys_users_rewarded.get_mut(survey_id) {
+            rewarded.insert(participant.clone(), true);
+        }
+        self.record_reward(survey_id, participant, nft_contract_id, token_id);
+    }
+
+    /// Gives back the slot of a reward whose mint failed, its token ID stays unused.
+    fn release_reward(&mut self, survey_id: &String, participant: &AccountId) {
+        let survey = self
+            .surveys
+            .get_mut(survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.participants_rewarded -= 1;
+        if let Some(rewarded) = self.surveys_users_rewarded.get_mut(survey_id) {
+            rewarded.remove(participant);
+        }
+    }
+
+    /// Charges the storage added since `initial_storage` plus `prepaid_bytes`
+    /// for `survey_id` to its creator and adds it to the survey's stake. Panics
+    /// unless the creator's storage balance and `attached` cover it all.
+    /// Returns the part taken from `attached`.
+    fn charge_survey_storage(
+        &mut self,
+        survey_id: &str,
+        initial_storage: u64,
+        prepaid_bytes: u64,
+        attached: NearToken,
+    ) -> NearToken {
+        let survey_creator = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest()
+            .survey_creator;
+        self.flush_survey_storage(survey_id, &survey_creator);
+
+        let cost = Self::storage_cost_since(initial_storage)
+            .saturating_add(env::storage_byte_cost().saturating_mul(prepaid_bytes as u128));
+        let (charged, from_attached) = self.charge_storage(&survey_creator, cost, attached);
+        assert!(
+            charged == cost,
+            "Not enough storage balance. Required: {}, see storage_deposit",
+            cost
+        );
+
+        let survey = self
+            .surveys
+            .get_mut(survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.storage_stake = survey.storage_stake.saturating_add(charged);
+        from_attached
     }
 
-    fn assert_owner(&self) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner,
-            "Only the owner can call this method"
+    /// Storage deposit `create_survey` requires on top of the fees.
+    fn survey_storage_fee(&self) -> NearToken {
+        env::storage_byte_cost()
+            .saturating_mul(SURVEY_STORAGE_BYTES)
+            .saturating_add(self.storage_balance_bounds().min)
+    }
+
+    /// Writes the cached entries of the survey collections to storage, so that
+    /// `env::storage_usage` accounts for them.
+    fn flush_survey_storage(&mut self, survey_id: &str, survey_creator: &AccountId) {
+        if let Some(survey_ids) = self.surveys_by_creator.get_mut(survey_creator) {
+            survey_ids.flush();
+        }
+        if let Some(rewarded) = self.surveys_users_rewarded.get_mut(survey_id) {
+            rewarded.flush();
+        }
+        if let Some(participants) = self.survey_participants.get_mut(survey_id) {
+            participants.flush();
+        }
+        self.surveys.flush();
+        self.survey_ids.flush();
+        self.surveys_by_creator.flush();
+        self.surveys_users_rewarded.flush();
+        self.rewards.flush();
+        self.survey_participants.flush();
+        self.participant_surveys.flush();
+    }
+
+    fn set_survey_status(&mut self, survey_id: String, status: SurveyStatus) {
+        let survey_creator = {
+            let survey = self
+                .surveys
+                .get(&survey_id)
+                .expect("Survey does not exist")
+                .latest();
+            survey.survey_creator.clone()
+        };
+        self.assert_creator_or_role(
+            &survey_id,
+            &survey_creator,
+            &[Role::SurveyManager, Role::Pauser],
         );
+
+        let survey = self
+            .surveys
+            .get_mut(&survey_id)
+            .expect("Survey does not exist")
+            .latest_mut();
+        survey.transition_to(status);
+
+        SurveyEvent::SurveyStatusChanged { survey_id, status }.emit();
+    }
+
+    /// Balance the survey collection account is created with, covering the storage
+    /// of the NFT contract code.
+    fn deploy_fee() -> NearToken {
+        NearToken::from_yoctonear(
+            env::storage_byte_cost()
+                .saturating_mul(NFT_WASM_CODE.len() as u128)
+                .as_yoctonear()
+                + 5 * 10u128.pow(23),
+        )
+    }
+
+    fn mint(
+        promise: Promise,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+        deposit: NearToken,
+    ) -> Promise {
+        let args = MintArgs {
+            token_id,
+            receiver_id,
+            token_metadata,
+        };
+
+        promise.function_call(
+            "nft_mint".to_owned(),
+            serde_json::to_vec(&args).unwrap(),
+            deposit,
+            TGAS.saturating_mul(5),
+        )
     }
 
-    fn assert_manager(&self) {
+    fn assert_mint_deposit(deposit: NearToken) {
         assert!(
-            *self
-                .managers
-                .get(&env::predecessor_account_id())
-                .unwrap_or(&false),
-            "Only a manager can call this method"
+            deposit >= MINT_DEPOSIT,
+            "Attached deposit must cover the mint: {}",
+            MINT_DEPOSIT
         );
     }
 
-    fn creator_or_manager(&self, survey_creator: &AccountId) {
+    /// Number of mints the prepaid gas covers, at most `REWARD_BATCH_LIMIT`.
+    fn reward_batch_limit() -> usize {
+        let available = env::prepaid_gas()
+            .saturating_sub(env::used_gas())
+            .saturating_sub(BATCH_GAS_RESERVE);
+        let batch_limit = (available.as_gas() / MINT_GAS.as_gas()) as usize;
+        assert!(batch_limit > 0, "Not enough gas attached");
+        batch_limit.min(REWARD_BATCH_LIMIT)
+    }
+
+    /// Checks a survey ID chosen by the caller, or assigns the next free
+    /// `q-<number>` ID when there is none.
+    fn claim_survey_id(&mut self, survey_id: Option<String>) -> String {
+        if let Some(survey_id) = survey_id {
+            assert!(
+                !survey_id.starts_with(GENERATED_ID_PREFIX),
+                "Survey IDs starting with {} are assigned by the contract",
+                GENERATED_ID_PREFIX
+            );
+            self.assert_new_survey_id(&survey_id);
+            return survey_id;
+        }
+
+        // Surveys moved over from a v1 state may already use the prefix.
+        loop {
+            let survey_id = format!("{}{}", GENERATED_ID_PREFIX, self.next_survey_number);
+            self.next_survey_number += 1;
+            if !self.is_survey_id_taken(&survey_id) {
+                return survey_id;
+            }
+        }
+    }
+
+    /// Survey IDs must be valid subaccount names: 2 to 64 lowercase letters and
+    /// digits, separated by single `-` or `_`.
+    fn assert_new_survey_id(&self, survey_id: &str) {
         assert!(
-            env::predecessor_account_id() == *survey_creator
-                || *self
-                    .managers
-                    .get(&env::predecessor_account_id())
-                    .unwrap_or(&false),
-            "Only the survey creator or a manager can call this method"
+            env::is_valid_account_id(survey_id.as_bytes()) && !survey_id.contains('.'),
+            "Invalid survey ID, use 2 to 64 lowercase letters and digits separated by - or _"
         );
+        assert!(!self.is_survey_id_taken(survey_id), "Survey already exists");
+    }
+
+    /// IDs stay taken after archiving, and v1 surveys that were not moved with
+    /// `migrate_v1_surveys` yet keep theirs too.
+    fn is_survey_id_taken(&self, survey_id: &str) -> bool {
+        self.surveys.contains_key(survey_id)
+            || self.archived_surveys.contains_key(survey_id)
+            || Self::is_v1_survey(survey_id)
     }
 }
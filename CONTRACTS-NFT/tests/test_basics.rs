@@ -18,10 +18,59 @@ struct Survey {
     participants_limit: u64,
     nft_contract_id: AccountId,
     participants_rewarded: u64,
-    is_canceled: bool,
+    status: String,
+    next_token_id: u64,
+    storage_stake: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct StorageBalance {
+    total: String,
+    available: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct SurveySummary {
+    survey_creator: AccountId,
+    participants_rewarded: u64,
+    status: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct RewardRecord {
+    survey_id: String,
+    participant: AccountId,
+    token_id: String,
+    rewarded_at: u64,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct ClaimableReward {
+    survey_id: String,
+    nft_contract_id: AccountId,
+    token_id: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct RewardResult {
+    participant: AccountId,
+    status: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+struct SurveyEntry {
+    survey_id: String,
+    survey: Survey,
 }
 
 #[tokio::test]
+#[allow(clippy::bool_assert_comparison)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let sandbox = sandbox().await?;
     let contract_wasm = near_workspaces::compile_project("./").await?;
@@ -82,12 +131,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         server_account.id().to_string()
     );
 
+    let outcome = deployer_account
+        .call(contract.id(), "set_fee_config")
+        .args_json(json!({"fee_config": {"per_participant_fee": NearToken::from_millinear(10), "base_fee": NearToken::from_millinear(20)}}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_required_deposit_outcome: serde_json::Value = contract
+        .view("get_required_deposit")
+        .args_json(json!({"participants_limit": 5}))
+        .await?
+        .json()?;
+    assert_eq!(
+        get_required_deposit_outcome["gas_fee"],
+        json!(NearToken::from_millinear(70))
+    );
+
     test_survey_create(&user_account, &contract).await?;
+
+    // Rewards are charged to the storage balance of the survey creator, which
+    // the survey creation used up.
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(
+            json!({"survey_id": SURVEY_ID, "participant": root.id(), "metadata": token_metadata()}),
+        )
+        .deposit(NearToken::from_yoctonear(10u128.pow(22)))
+        .gas(TGAS.saturating_mul(150))
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", outcome.into_result().unwrap_err()).contains("Not enough storage balance")
+    );
+
+    let outcome = user_account
+        .call(contract.id(), "storage_deposit")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
     test_reward(&root, &server_account, &contract).await?;
+    test_archive(&user_account, &server_account, &contract).await?;
+    test_claim(&root, &user_account, &server_account, &contract).await?;
+    test_batch_reward(&root, &user_account, &server_account, &contract).await?;
 
     Ok(())
 }
 
+#[allow(clippy::needless_borrow)]
 async fn test_survey_create(
     user_account: &Account,
     contract: &Contract,
@@ -104,6 +198,7 @@ async fn test_survey_create(
         reference_hash: None,
     };
 
+    let balance_prev = user_account.view_account().await?.balance;
     let outcome = user_account
         .call(&contract.id(), "create_survey")
         .args_json(json!({"survey_id": SURVEY_ID, "participants_limit": 3u64, "gas_fee": fee_amount.to_string(), "metadata": metadata}))
@@ -121,6 +216,38 @@ async fn test_survey_create(
 
     assert!(outcome.is_success());
 
+    // The deposit above the deploy, gas and storage fees is refunded.
+    let withdrawal = format!("{{\"account_id\":\"{}\"", user_account.id());
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"withdrawal\"") && log.contains(&withdrawal)));
+    let required_deposit: serde_json::Value = contract
+        .view("get_required_deposit")
+        .args_json(json!({"participants_limit": 3}))
+        .await?
+        .json()?;
+    let storage_fee: NearToken = serde_json::from_value(required_deposit["storage_fee"].clone())?;
+    let required_deposit: NearToken =
+        serde_json::from_value(required_deposit["common_fee"].clone())?;
+    let balance_after = user_account.view_account().await?.balance;
+    let spent = balance_prev.saturating_sub(balance_after);
+    // The quoted storage fee is an upper bound.
+    assert!(spent > required_deposit.saturating_sub(storage_fee));
+    assert!(spent < required_deposit.saturating_add(NearToken::from_millinear(100)));
+
+    // Invalid, reserved and taken IDs are rejected before the collection is deployed.
+    for survey_id in ["Survey.1", "q-7", SURVEY_ID] {
+        let outcome = user_account
+            .call(contract.id(), "create_survey")
+            .args_json(json!({"survey_id": survey_id, "participants_limit": 3u64, "gas_fee": fee_amount.to_string(), "metadata": metadata}))
+            .deposit(NearToken::from_yoctonear(6 * 10u128.pow(24)))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(!outcome.is_success());
+    }
+
     let get_survey_outcome: Survey = contract
         .view("get_survey")
         .args_json(json!({"survey_id": SURVEY_ID}))
@@ -131,7 +258,31 @@ async fn test_survey_create(
         user_account.id().to_string()
     );
     assert_eq!(get_survey_outcome.participants_rewarded, 0);
-    assert_eq!(get_survey_outcome.is_canceled, false);
+    assert_eq!(get_survey_outcome.status, "Active");
+    assert_ne!(get_survey_outcome.storage_stake, "0");
+
+    // The survey storage was paid from the deposit, which registered the creator.
+    let storage_balance: Option<StorageBalance> = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert!(storage_balance.is_some());
+
+    let get_survey_count_outcome: u64 = contract
+        .view("get_survey_count")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_count_outcome, 1);
+
+    let get_surveys_by_creator_outcome: Vec<SurveyEntry> = contract
+        .view("get_surveys_by_creator")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(get_surveys_by_creator_outcome.len(), 1);
+    assert_eq!(get_surveys_by_creator_outcome[0].survey_id, SURVEY_ID);
 
     // tokio::time::sleep(std::time::Duration::from_secs(5)).await;
 
@@ -148,6 +299,7 @@ async fn test_survey_create(
     Ok(())
 }
 
+#[allow(clippy::needless_borrow)]
 async fn test_reward(
     root_account: &Account,
     server_account: &Account,
@@ -175,7 +327,6 @@ async fn test_reward(
         reference_hash: None,
     };
 
-    let reward_amount = 10u128.pow(22);
     let outcome = server_account
         .call(&contract.id(), "reward_participant")
         .args_json(json!({"survey_id": SURVEY_ID, "participant": rewarded_user_account_1.id(), "metadata": metadata}))
@@ -209,6 +360,25 @@ async fn test_reward(
         .into_result()
         .expect_err("Participant already rewarded");
 
+    let is_rewarded_outcome: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": SURVEY_ID, "account_id": rewarded_user_account_1.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded_outcome);
+
+    let get_survey_participants_outcome: Vec<RewardRecord> = contract
+        .view("get_survey_participants")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_participants_outcome.len(), 1);
+    assert_eq!(
+        get_survey_participants_outcome[0].participant.to_string(),
+        rewarded_user_account_1.id().to_string()
+    );
+    assert_eq!(get_survey_participants_outcome[0].token_id, "0");
+
     let rewarded_user_account_2 = root_account
         .create_subaccount("rewarded_user_2")
         .initial_balance(ONE_HUNDRED_NEAR)
@@ -262,3 +432,487 @@ async fn test_reward(
 
     Ok(())
 }
+
+async fn test_archive(
+    user_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let storage_balance_prev: StorageBalance = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+
+    let outcome = server_account
+        .call(contract.id(), "archive_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+
+    let summary: Option<SurveySummary> = contract
+        .view("get_archived_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    let summary = summary.expect("Survey is archived");
+    assert_eq!(summary.survey_creator, *user_account.id());
+    assert_eq!(summary.participants_rewarded, 3);
+    assert_eq!(summary.status, "Completed");
+
+    let outcome = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await;
+    assert!(outcome.is_err());
+
+    let get_survey_participants_outcome: Vec<RewardRecord> = contract
+        .view("get_survey_participants")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert!(get_survey_participants_outcome.is_empty());
+
+    // The creator gets the freed storage back.
+    let storage_balance_after: StorageBalance = contract
+        .view("storage_balance_of")
+        .args_json(json!({"account_id": user_account.id()}))
+        .await?
+        .json()?;
+    assert!(
+        storage_balance_after.available.parse::<u128>()?
+            > storage_balance_prev.available.parse::<u128>()?
+    );
+
+    // Archiving again changes nothing.
+    let outcome = server_account
+        .call(contract.id(), "archive_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"survey_archived\"")));
+    assert!(outcome.json::<bool>()?);
+
+    Ok(())
+}
+
+fn token_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: Some("Olympus Mons".into()),
+        description: Some("The tallest mountain in the charted solar system".into()),
+        media: None,
+        media_hash: None,
+        copies: Some(1u64),
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    }
+}
+
+/// Creates a survey whose collection is named after `survey_id`.
+async fn create_survey(
+    user_account: &Account,
+    contract: &Contract,
+    survey_id: &str,
+    participants_limit: u64,
+) -> Result<Survey, Box<dyn std::error::Error>> {
+    let metadata = NFTContractMetadata {
+        spec: "nft-1.0.0".to_string(),
+        name: "Quizzler NFT".to_string(),
+        symbol: "QUIZ".to_string(),
+        icon: None,
+        base_uri: None,
+        reference: None,
+        reference_hash: None,
+    };
+
+    let outcome = user_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": survey_id, "participants_limit": participants_limit, "gas_fee": NearToken::from_millinear(10).saturating_mul(participants_limit as u128).saturating_add(NearToken::from_millinear(20)), "metadata": metadata}))
+        .deposit(NearToken::from_near(6))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let survey: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": survey_id}))
+        .await?
+        .json()?;
+    Ok(survey)
+}
+
+async fn test_claim(
+    root_account: &Account,
+    user_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const CLAIM_SURVEY_ID: &str = "3jdkwo-pq83na-x8s0qm-1lsmv02";
+    let survey = create_survey(user_account, contract, CLAIM_SURVEY_ID, 2).await?;
+
+    let claiming_user_account = root_account
+        .create_subaccount("claiming_user")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    // A reward credited without the mint deposit could never be claimed.
+    let outcome = server_account
+        .call(contract.id(), "credit_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "participant": claiming_user_account.id(), "metadata": token_metadata()}))
+        .transact()
+        .await?;
+    outcome
+        .into_result()
+        .expect_err("Attached deposit must cover the mint");
+
+    let outcome = server_account
+        .call(contract.id(), "credit_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "participant": claiming_user_account.id(), "metadata": token_metadata()}))
+        .deposit(NearToken::from_yoctonear(10u128.pow(22)))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The credited reward holds its slot and token ID until it is claimed.
+    let outcome = server_account
+        .call(contract.id(), "reward_participant")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "participant": claiming_user_account.id(), "metadata": token_metadata()}))
+        .deposit(NearToken::from_yoctonear(10u128.pow(22)))
+        .gas(TGAS.saturating_mul(150))
+        .transact()
+        .await?;
+    outcome
+        .into_result()
+        .expect_err("Participant already rewarded");
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 1);
+    assert_eq!(get_survey_outcome.next_token_id, 1);
+
+    let claimable: Vec<ClaimableReward> = contract
+        .view("get_claimable")
+        .args_json(json!({"account_id": claiming_user_account.id()}))
+        .await?
+        .json()?;
+    assert_eq!(claimable.len(), 1);
+    assert_eq!(claimable[0].survey_id, CLAIM_SURVEY_ID);
+    assert_eq!(claimable[0].nft_contract_id, survey.nft_contract_id);
+    assert_eq!(claimable[0].token_id, "0");
+
+    let outcome = claiming_user_account
+        .call(contract.id(), "claim_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID}))
+        .gas(TGAS.saturating_mul(150))
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID, "account_id": claiming_user_account.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    let token: serde_json::Value = claiming_user_account
+        .view(&survey.nft_contract_id, "nft_token")
+        .args_json(json!({"token_id": "0"}))
+        .await?
+        .json()?;
+    assert_eq!(token["owner_id"], json!(claiming_user_account.id()));
+
+    let claimable: Vec<ClaimableReward> = contract
+        .view("get_claimable")
+        .args_json(json!({"account_id": claiming_user_account.id()}))
+        .await?
+        .json()?;
+    assert!(claimable.is_empty());
+
+    let outcome = claiming_user_account
+        .call(contract.id(), "claim_reward")
+        .args_json(json!({"survey_id": CLAIM_SURVEY_ID}))
+        .gas(TGAS.saturating_mul(150))
+        .transact()
+        .await?;
+    outcome.into_result().expect_err("No claimable reward");
+
+    Ok(())
+}
+
+async fn test_batch_reward(
+    root_account: &Account,
+    user_account: &Account,
+    server_account: &Account,
+    contract: &Contract,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const BATCH_SURVEY_ID: &str = "8kcn2m-wq01bz-ya7d4e-3hsp5rt";
+    create_survey(user_account, contract, BATCH_SURVEY_ID, 21).await?;
+
+    let participants = (0..22)
+        .map(|index| format!("batch-{}.{}", index, root_account.id()).parse())
+        .collect::<Result<Vec<AccountId>, _>>()?;
+
+    let deposit_per_mint = NearToken::from_yoctonear(10u128.pow(22));
+
+    // A call without gas for a single mint is rejected before any slot is reserved.
+    let outcome = server_account
+        .call(contract.id(), "reward_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants": participants, "metadata": token_metadata()}))
+        .deposit(deposit_per_mint.saturating_mul(20))
+        .gas(TGAS.saturating_mul(30))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err()).contains("Not enough gas attached"));
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 0);
+
+    // 20 mints fit into one call, the duplicate is skipped and the last two are
+    // left for the next call. The deposit does not split evenly.
+    let mut batch = vec![participants[0].clone()];
+    batch.extend(participants.iter().cloned());
+    let outcome = server_account
+        .call(contract.id(), "reward_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants": batch, "metadata": token_metadata()}))
+        .deposit(deposit_per_mint.saturating_mul(20).saturating_add(NearToken::from_yoctonear(1)))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let withdrawal = json!({"account_id": server_account.id(), "amount": "1"}).to_string();
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"withdrawal\"") && log.contains(&withdrawal)));
+
+    let results: Vec<RewardResult> = outcome.json()?;
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|result| result.status.as_str())
+        .collect();
+    assert_eq!(results.len(), 23);
+    assert_eq!(statuses[0], "Scheduled");
+    assert_eq!(statuses[1], "AlreadyRewarded");
+    assert!(statuses[2..21].iter().all(|status| *status == "Scheduled"));
+    assert_eq!(statuses[21..], ["NotProcessed", "NotProcessed"]);
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 20);
+
+    let outcome = server_account
+        .call(contract.id(), "reward_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "participants": participants[19..], "metadata": token_metadata()}))
+        .deposit(deposit_per_mint)
+        .max_gas()
+        .transact()
+        .await?;
+    let results: Vec<RewardResult> = outcome.json()?;
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|result| result.status.as_str())
+        .collect();
+    assert_eq!(statuses, ["AlreadyRewarded", "Scheduled", "LimitReached"]);
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.participants_rewarded, 21);
+    assert_eq!(get_survey_outcome.status, "Completed");
+
+    let get_survey_participants_outcome: Vec<RewardRecord> = contract
+        .view("get_survey_participants")
+        .args_json(json!({"survey_id": BATCH_SURVEY_ID, "from_index": 20}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_participants_outcome.len(), 1);
+    assert_eq!(
+        get_survey_participants_outcome[0].participant,
+        participants[20]
+    );
+    assert_eq!(get_survey_participants_outcome[0].token_id, "20");
+
+    Ok(())
+}
+
+#[derive(near_sdk::borsh::BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+struct QuizzlerV1 {
+    owner: AccountId,
+    gas_station: AccountId,
+    managers: Vec<u8>,
+    surveys: Vec<u8>,
+    surveys_users_rewarded: Vec<u8>,
+}
+
+#[derive(near_sdk::borsh::BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+struct SurveyV1 {
+    survey_creator: AccountId,
+    participants_limit: u64,
+    nft_contract_id: AccountId,
+    participants_rewarded: u64,
+    is_canceled: bool,
+}
+
+#[tokio::test]
+async fn test_migrate_v1() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = sandbox().await?;
+    let contract_wasm = near_workspaces::compile_project("./").await?;
+
+    let root = sandbox.root_account()?;
+
+    let owner_account = root
+        .create_subaccount("owner")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap();
+
+    let contract = root
+        .create_subaccount("legacy")
+        .initial_balance(ONE_HUNDRED_NEAR)
+        .transact()
+        .await?
+        .unwrap()
+        .deploy(&contract_wasm)
+        .await?
+        .unwrap();
+
+    let state = QuizzlerV1 {
+        owner: owner_account.id().clone(),
+        gas_station: root.id().clone(),
+        managers: b"m".to_vec(),
+        surveys: b"s".to_vec(),
+        surveys_users_rewarded: b"surveys_users_rewarded".to_vec(),
+    };
+    sandbox
+        .patch_state(contract.id(), b"STATE", &near_sdk::borsh::to_vec(&state)?)
+        .await?;
+
+    let survey = SurveyV1 {
+        survey_creator: owner_account.id().clone(),
+        participants_limit: 10,
+        nft_contract_id: format!("{}.{}", SURVEY_ID, contract.id()).parse()?,
+        participants_rewarded: 3,
+        is_canceled: true,
+    };
+    let survey_key = [b"s".to_vec(), near_sdk::borsh::to_vec(SURVEY_ID)?].concat();
+    sandbox
+        .patch_state(
+            contract.id(),
+            &survey_key,
+            &near_sdk::borsh::to_vec(&survey)?,
+        )
+        .await?;
+
+    let legacy_prefix = format!("{}-r", SURVEY_ID).into_bytes();
+    let rewarded_key = [
+        b"surveys_users_rewarded".to_vec(),
+        near_sdk::borsh::to_vec(SURVEY_ID)?,
+    ]
+    .concat();
+    sandbox
+        .patch_state(
+            contract.id(),
+            &rewarded_key,
+            &near_sdk::borsh::to_vec(&legacy_prefix)?,
+        )
+        .await?;
+    let participant_key = [legacy_prefix, near_sdk::borsh::to_vec(root.id().as_str())?].concat();
+    sandbox
+        .patch_state(
+            contract.id(),
+            &participant_key,
+            &near_sdk::borsh::to_vec(&true)?,
+        )
+        .await?;
+
+    let outcome = contract
+        .call("migrate")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // IDs of surveys that were not moved yet stay taken.
+    let outcome = owner_account
+        .call(contract.id(), "create_survey")
+        .args_json(json!({"survey_id": SURVEY_ID, "participants_limit": 3u64, "gas_fee": "0", "metadata": token_metadata()}))
+        .deposit(NearToken::from_near(6))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err()).contains("Survey already exists"));
+
+    let outcome = owner_account
+        .call(contract.id(), "migrate_v1_surveys")
+        .args_json(json!({"survey_ids": [SURVEY_ID]}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let get_survey_outcome: Survey = contract
+        .view("get_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(get_survey_outcome.status, "Canceled");
+    assert_eq!(get_survey_outcome.next_token_id, 3);
+
+    // Participants rewarded by the v1 contract stay under the `{survey_id}-r` prefix.
+    let is_rewarded: bool = contract
+        .view("is_rewarded")
+        .args_json(json!({"survey_id": SURVEY_ID, "account_id": root.id()}))
+        .await?
+        .json()?;
+    assert!(is_rewarded);
+
+    // Their missing reward records do not keep the survey from being archived.
+    let outcome = owner_account
+        .call(contract.id(), "archive_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.json::<bool>()?);
+
+    let summary: Option<SurveySummary> = contract
+        .view("get_archived_survey")
+        .args_json(json!({"survey_id": SURVEY_ID}))
+        .await?
+        .json()?;
+    assert_eq!(
+        summary.expect("Survey is archived").participants_rewarded,
+        3
+    );
+
+    Ok(())
+}
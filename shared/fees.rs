@@ -0,0 +1,136 @@
+//! Gas fees of NEAR-funded surveys. Both contracts include this file with
+//! `#[path]`, so the fee settings behave the same in each of them.
+
+use near_sdk::{near, AccountId, NearToken};
+
+use crate::{Quizzler, QuizzlerExt, SurveyEvent};
+
+const GAS_FEE_PER_PARTICIPANT: NearToken = NearToken::from_yoctonear(15 * 10u128.pow(21)); // 0.015Ⓝ until set_fee_config
+
+/// Fee forwarded to the gas station when a NEAR-funded survey is created: a
+/// flat `base_fee` plus `per_participant_fee` for every participant.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, PartialEq, Debug)]
+pub struct FeeConfig {
+    per_participant_fee: NearToken,
+    base_fee: NearToken,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            per_participant_fee: GAS_FEE_PER_PARTICIPANT,
+            base_fee: NearToken::from_yoctonear(0),
+        }
+    }
+}
+
+/// Fee config override of one survey. It only applies to a survey created by
+/// `survey_creator`, so nobody else can claim the survey ID to use it.
+#[near(serializers = [borsh])]
+pub struct SurveyFeeConfig {
+    survey_creator: AccountId,
+    fee_config: FeeConfig,
+}
+
+impl FeeConfig {
+    pub(crate) fn gas_fee(&self, participants_limit: u64) -> NearToken {
+        self.participants_fee(participants_limit)
+            .saturating_add(self.base_fee)
+    }
+
+    /// Fee for `participants` without the base fee, charged when a survey grows.
+    pub(crate) fn participants_fee(&self, participants: u64) -> NearToken {
+        self.per_participant_fee
+            .saturating_mul(participants as u128)
+    }
+}
+
+#[near]
+impl Quizzler {
+    pub fn set_fee_config(&mut self, fee_config: FeeConfig) {
+        self.assert_admin();
+        self.fee_config = fee_config.clone();
+
+        SurveyEvent::FeeConfigChanged {
+            survey_id: None,
+            survey_creator: None,
+            fee_config: Some(fee_config),
+        }
+        .emit();
+    }
+
+    /// Overrides the fee config for the survey `survey_creator` creates as
+    /// `survey_id`, `None` falls back to the contract-wide one. Can be set
+    /// before the survey is created.
+    pub fn set_survey_fee_config(
+        &mut self,
+        survey_id: String,
+        survey_creator: AccountId,
+        fee_config: Option<FeeConfig>,
+    ) {
+        self.assert_admin();
+        match &fee_config {
+            Some(fee_config) => self.survey_fee_configs.insert(
+                survey_id.clone(),
+                SurveyFeeConfig {
+                    survey_creator: survey_creator.clone(),
+                    fee_config: fee_config.clone(),
+                },
+            ),
+            None => self.survey_fee_configs.remove(&survey_id),
+        };
+
+        SurveyEvent::FeeConfigChanged {
+            survey_id: Some(survey_id),
+            survey_creator: Some(survey_creator),
+            fee_config,
+        }
+        .emit();
+    }
+
+    /// Returns the fee config that applies to `survey_id` when `survey_creator`
+    /// creates it, or the contract-wide one without both.
+    pub fn get_fee_config(
+        &self,
+        survey_id: Option<String>,
+        survey_creator: Option<AccountId>,
+    ) -> FeeConfig {
+        self.fee_config_for(survey_id.as_deref(), survey_creator.as_ref())
+            .clone()
+    }
+}
+
+impl Quizzler {
+    /// The override of `survey_id` only counts for the creator it was set for.
+    pub(crate) fn fee_config_for(
+        &self,
+        survey_id: Option<&str>,
+        survey_creator: Option<&AccountId>,
+    ) -> &FeeConfig {
+        survey_id
+            .and_then(|survey_id| self.survey_fee_configs.get(survey_id))
+            .filter(|survey_fee_config| Some(&survey_fee_config.survey_creator) == survey_creator)
+            .map(|survey_fee_config| &survey_fee_config.fee_config)
+            .unwrap_or(&self.fee_config)
+    }
+
+    /// Panics if `gas_fee` does not cover the fee of a new survey.
+    pub(crate) fn assert_gas_fee(
+        &self,
+        survey_id: &str,
+        survey_creator: &AccountId,
+        participants_limit: u64,
+        gas_fee: NearToken,
+    ) {
+        let fee_needed = self
+            .fee_config_for(Some(survey_id), Some(survey_creator))
+            .gas_fee(participants_limit);
+        assert!(
+            gas_fee >= fee_needed,
+            "Gas fee is not sufficient. Required: {}, Attached: {}",
+            fee_needed,
+            gas_fee
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Contract upgrades and the migration of a v1 state, shared by both contracts
+//! through `#[path]` like `fees.rs`.
+
+use near_sdk::store::LookupMap;
+use near_sdk::{borsh, env, near, AccountId, Gas, NearToken, Promise};
+
+use crate::{Quizzler, QuizzlerExt, Role, RoleScope, SurveyV1, VersionedSurvey};
+
+const MIGRATE_GAS: Gas = Gas::from_tgas(100);
+
+/// Contract state as written by the first version of the contract.
+#[near(serializers = [borsh])]
+#[allow(dead_code)]
+pub struct QuizzlerV1 {
+    owner: AccountId,
+    gas_station: AccountId,
+    managers: LookupMap<AccountId, bool>,
+    surveys: LookupMap<String, SurveyV1>,
+    surveys_users_rewarded: LookupMap<String, LookupMap<AccountId, bool>>,
+}
+
+#[near]
+impl Quizzler {
+    /// Deploys the wasm passed as the raw call input and runs `migrate` on it.
+    pub fn update_contract(&self) -> Promise {
+        self.assert_owner();
+        let code = env::input().expect("No contract code was attached");
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                Vec::new(),
+                NearToken::from_yoctonear(0),
+                MIGRATE_GAS,
+            )
+            .as_return()
+    }
+
+    /// Reads the state left by the previous code. A current state is kept as is,
+    /// a v1 state gets the current layout with its owner keeping the manager
+    /// roles. The v1 maps cannot be enumerated, their entries are moved with
+    /// `migrate_v1_surveys` and `migrate_v1_managers`, then the owner calls
+    /// `finish_v1_migration`. States of the builds
+    /// between v1 and the current one are not supported.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("The contract is not initialized");
+        if let Ok(contract) = borsh::from_slice::<Self>(&state) {
+            return contract;
+        }
+
+        let old: QuizzlerV1 = borsh::from_slice(&state).expect("Unknown contract state");
+        // The kept entries point to the `{survey_id}-r` maps of the v1 surveys, which
+        // stay in use. Their prefix cannot collide with a `StorageKey` prefix.
+        let mut contract = Self::initial_state(old.owner, old.gas_station);
+        contract.surveys_users_rewarded = old.surveys_users_rewarded;
+        contract.v1_migration_pending = true;
+        contract
+    }
+
+    /// Moves surveys of a v1 state into the survey list. The unpaid budget of
+    /// native surveys is added to the escrow; unknown or already moved IDs are
+    /// skipped, IDs that are taken by a current survey panic.
+    pub fn migrate_v1_surveys(&mut self, survey_ids: Vec<String>) {
+        self.assert_owner();
+        let mut legacy_surveys: LookupMap<String, SurveyV1> = LookupMap::new(b"s");
+
+        for survey_id in survey_ids {
+            let Some(survey) = legacy_surveys.remove(&survey_id) else {
+                continue;
+            };
+            // Only possible for IDs taken before v1 IDs were reserved.
+            assert!(
+                !self.surveys.contains_key(&survey_id)
+                    && !self.archived_surveys.contains_key(&survey_id),
+                "Survey {} already exists",
+                survey_id
+            );
+            self.escrow_balance = self.escrow_balance.saturating_add(survey.escrowed_budget());
+            self.insert_survey(survey_id, VersionedSurvey::V1(survey));
+        }
+    }
+
+    /// Marks every v1 survey as moved, which releases the balance outside of
+    /// the escrow to `emergency_withdraw`. The budget of a v1 survey moved after
+    /// this call is unprotected until it is moved.
+    pub fn finish_v1_migration(&mut self) {
+        self.assert_owner();
+        self.v1_migration_pending = false;
+    }
+
+    /// Grants the global manager roles to the listed v1 managers.
+    pub fn migrate_v1_managers(&mut self, managers: Vec<AccountId>) {
+        self.assert_owner();
+        let mut legacy_managers: LookupMap<AccountId, bool> = LookupMap::new(b"m");
+
+        for manager in managers {
+            if legacy_managers.remove(&manager) == Some(true) {
+                self.insert_role(Role::SurveyManager, RoleScope::Global, &manager);
+                self.insert_role(Role::Rewarder, RoleScope::Global, &manager);
+            }
+        }
+    }
+}
+
+impl Quizzler {
+    /// Whether `survey_id` is still in the survey map of a v1 state.
+    pub(crate) fn is_v1_survey(survey_id: &str) -> bool {
+        LookupMap::<String, SurveyV1>::new(b"s").contains_key(survey_id)
+    }
+}
@@ -0,0 +1,83 @@
+//! Contract-wide and per-method pause flags, shared by both contracts through
+//! `#[path]` like `fees.rs`.
+
+use near_sdk::near;
+
+use crate::{Quizzler, QuizzlerExt, Role, SurveyEvent};
+
+/// Entry points that can be paused on their own with `set_method_paused`.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PausableMethod {
+    Create,
+    Reward,
+    Cancel,
+}
+
+/// `paused` stops every state-changing entry point except the ones returning
+/// funds to creators and the administrative ones.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Default)]
+pub struct PauseState {
+    paused: bool,
+    create_paused: bool,
+    reward_paused: bool,
+    cancel_paused: bool,
+}
+
+#[near]
+impl Quizzler {
+    pub fn set_paused(&mut self, paused: bool) {
+        self.assert_global_role(Role::Pauser);
+        self.pause_state.paused = paused;
+
+        SurveyEvent::PauseChanged {
+            method: None,
+            paused,
+        }
+        .emit();
+    }
+
+    pub fn set_method_paused(&mut self, method: PausableMethod, paused: bool) {
+        self.assert_global_role(Role::Pauser);
+        match method {
+            PausableMethod::Create => self.pause_state.create_paused = paused,
+            PausableMethod::Reward => self.pause_state.reward_paused = paused,
+            PausableMethod::Cancel => self.pause_state.cancel_paused = paused,
+        }
+
+        SurveyEvent::PauseChanged {
+            method: Some(method),
+            paused,
+        }
+        .emit();
+    }
+
+    pub fn get_pause_state(&self) -> PauseState {
+        self.pause_state.clone()
+    }
+}
+
+impl Quizzler {
+    /// Panics if the contract or `method` is paused. Cancels only stop for their
+    /// own flag, since they return the unused budget to the creator.
+    pub(crate) fn assert_not_paused(&self, method: Option<PausableMethod>) {
+        match method {
+            Some(PausableMethod::Create) => {
+                assert!(!self.pause_state.create_paused, "Survey creation is paused")
+            }
+            Some(PausableMethod::Reward) => {
+                assert!(!self.pause_state.reward_paused, "Rewards are paused")
+            }
+            Some(PausableMethod::Cancel) => {
+                assert!(
+                    !self.pause_state.cancel_paused,
+                    "Survey cancellation is paused"
+                );
+                return;
+            }
+            None => {}
+        }
+        assert!(!self.pause_state.paused, "Contract is paused");
+    }
+}
@@ -0,0 +1,321 @@
+//! Ownership and role-based access control, shared by both contracts through
+//! `#[path]` like `fees.rs`.
+
+use near_sdk::store::IterableSet;
+use near_sdk::{borsh, env, near, AccountId};
+
+use crate::{Quizzler, QuizzlerExt, StorageKey, SurveyEvent, DEFAULT_PAGE_LIMIT};
+
+/// The owner and global admins pass every role check.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Role {
+    /// Grants and revokes roles and changes the contract settings.
+    Admin,
+    /// Cancels, resizes, pauses and resumes surveys.
+    SurveyManager,
+    /// Rewards and credits participants.
+    Rewarder,
+    /// Pauses and resumes surveys and the whole contract.
+    Pauser,
+    /// Withdraws the free balance of the contract.
+    FeeCollector,
+}
+
+/// Where a role applies: everywhere, to one survey or to every survey of a creator.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum RoleScope {
+    Global,
+    Survey(String),
+    Creator(AccountId),
+}
+
+#[near]
+impl Quizzler {
+    pub fn get_owner(&self) -> Option<AccountId> {
+        self.owner.clone()
+    }
+
+    pub fn get_proposed_owner(&self) -> Option<AccountId> {
+        self.proposed_owner.clone()
+    }
+
+    /// Starts an ownership transfer, `new_owner` takes over once it calls
+    /// `accept_ownership`. A new proposal replaces the pending one.
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        self.assert_owner();
+        self.proposed_owner = Some(new_owner.clone());
+
+        SurveyEvent::OwnershipProposed {
+            owner: env::predecessor_account_id(),
+            proposed_owner: new_owner,
+        }
+        .emit();
+    }
+
+    pub fn accept_ownership(&mut self) {
+        let new_owner = env::predecessor_account_id();
+        assert!(
+            self.proposed_owner.as_ref() == Some(&new_owner),
+            "Only the proposed owner can accept the ownership"
+        );
+
+        self.proposed_owner = None;
+        let previous_owner = self
+            .owner
+            .replace(new_owner.clone())
+            .expect("Ownership was renounced");
+
+        SurveyEvent::OwnershipTransferred {
+            previous_owner,
+            new_owner: Some(new_owner),
+        }
+        .emit();
+    }
+
+    /// Leaves the contract without an owner for good. `confirm` must be the
+    /// account ID of the contract itself.
+    pub fn renounce_ownership(&mut self, confirm: AccountId) {
+        self.assert_owner();
+        assert_eq!(
+            confirm,
+            env::current_account_id(),
+            "Confirmation does not match the contract account"
+        );
+
+        self.proposed_owner = None;
+        let previous_owner = self.owner.take().expect("Ownership was renounced");
+
+        SurveyEvent::OwnershipTransferred {
+            previous_owner,
+            new_owner: None,
+        }
+        .emit();
+    }
+
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId, scope: Option<RoleScope>) {
+        self.assert_admin();
+        self.insert_role(role, scope.unwrap_or(RoleScope::Global), &account_id);
+    }
+
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId, scope: Option<RoleScope>) {
+        self.assert_admin();
+        self.remove_role(role, scope.unwrap_or(RoleScope::Global), &account_id);
+    }
+
+    /// Checks a grant for exactly `scope`, global grants do not count for a
+    /// survey or creator scope here.
+    pub fn has_role(&self, role: Role, account_id: AccountId, scope: Option<RoleScope>) -> bool {
+        self.has_role_in(role, &scope.unwrap_or(RoleScope::Global), &account_id)
+    }
+
+    pub fn get_role_members(
+        &self,
+        role: Role,
+        scope: Option<RoleScope>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let Some(members) = self
+            .role_members
+            .get(&(role, scope.unwrap_or(RoleScope::Global)))
+        else {
+            return Vec::new();
+        };
+
+        members
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Grants or revokes the global `SurveyManager` and `Rewarder` roles together.
+    pub fn set_manager(&mut self, manager: AccountId, status: bool) {
+        self.assert_admin();
+        for role in [Role::SurveyManager, Role::Rewarder] {
+            if status {
+                self.insert_role(role, RoleScope::Global, &manager);
+            } else {
+                self.remove_role(role, RoleScope::Global, &manager);
+            }
+        }
+
+        SurveyEvent::ManagerChanged { manager, status }.emit();
+    }
+
+    pub fn is_manager(&self, manager: AccountId) -> bool {
+        self.has_role_in(Role::SurveyManager, &RoleScope::Global, &manager)
+    }
+
+    /// Lets the survey creator appoint an account that rewards the participants
+    /// of this survey only.
+    pub fn add_survey_rewarder(&mut self, survey_id: String, account_id: AccountId) {
+        self.assert_not_paused(None);
+
+        self.assert_creator_or_admin(&survey_id);
+        self.insert_role(Role::Rewarder, RoleScope::Survey(survey_id), &account_id);
+    }
+
+    pub fn remove_survey_rewarder(&mut self, survey_id: String, account_id: AccountId) {
+        self.assert_not_paused(None);
+
+        self.assert_creator_or_admin(&survey_id);
+        self.remove_role(Role::Rewarder, RoleScope::Survey(survey_id), &account_id);
+    }
+
+    pub fn get_survey_rewarders(
+        &self,
+        survey_id: String,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        self.get_role_members(
+            Role::Rewarder,
+            Some(RoleScope::Survey(survey_id)),
+            from_index,
+            limit,
+        )
+    }
+}
+
+impl Quizzler {
+    pub(crate) fn assert_owner(&self) {
+        assert!(
+            self.owner.as_ref() == Some(&env::predecessor_account_id()),
+            "Only the owner can call this method"
+        );
+    }
+
+    pub(crate) fn assert_admin(&self) {
+        assert!(
+            self.is_admin(&env::predecessor_account_id()),
+            "Only an admin can call this method"
+        );
+    }
+
+    pub(crate) fn assert_creator_or_admin(&self, survey_id: &str) {
+        let survey = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest();
+        let account_id = env::predecessor_account_id();
+        assert!(
+            account_id == survey.survey_creator || self.is_admin(&account_id),
+            "Only the survey creator or an admin can call this method"
+        );
+    }
+
+    pub(crate) fn assert_global_role(&self, role: Role) {
+        let account_id = env::predecessor_account_id();
+        assert!(
+            self.is_admin(&account_id) || self.has_role_in(role, &RoleScope::Global, &account_id),
+            "Only a {:?} can call this method",
+            role
+        );
+    }
+
+    pub(crate) fn assert_survey_role(&self, survey_id: &str, role: Role) {
+        let survey = self
+            .surveys
+            .get(survey_id)
+            .expect("Survey does not exist")
+            .latest();
+        assert!(
+            self.has_survey_role(
+                role,
+                survey_id,
+                &survey.survey_creator,
+                &env::predecessor_account_id()
+            ),
+            "Only a {:?} can call this method",
+            role
+        );
+    }
+
+    pub(crate) fn assert_creator_or_role(
+        &self,
+        survey_id: &str,
+        survey_creator: &AccountId,
+        roles: &[Role],
+    ) {
+        let account_id = env::predecessor_account_id();
+        assert!(
+            account_id == *survey_creator
+                || roles.iter().any(|role| {
+                    self.has_survey_role(*role, survey_id, survey_creator, &account_id)
+                }),
+            "Only the survey creator or a manager can call this method"
+        );
+    }
+
+    fn is_admin(&self, account_id: &AccountId) -> bool {
+        self.owner.as_ref() == Some(account_id)
+            || self.has_role_in(Role::Admin, &RoleScope::Global, account_id)
+    }
+
+    fn has_role_in(&self, role: Role, scope: &RoleScope, account_id: &AccountId) -> bool {
+        self.role_members
+            .get(&(role, scope.clone()))
+            .is_some_and(|members| members.contains(account_id))
+    }
+
+    /// Checks `role` granted globally, for the survey or for its creator.
+    fn has_survey_role(
+        &self,
+        role: Role,
+        survey_id: &str,
+        survey_creator: &AccountId,
+        account_id: &AccountId,
+    ) -> bool {
+        self.is_admin(account_id)
+            || [
+                RoleScope::Global,
+                RoleScope::Survey(survey_id.to_string()),
+                RoleScope::Creator(survey_creator.clone()),
+            ]
+            .iter()
+            .any(|scope| self.has_role_in(role, scope, account_id))
+    }
+
+    pub(crate) fn insert_role(&mut self, role: Role, scope: RoleScope, account_id: &AccountId) {
+        let key = (role, scope.clone());
+        let prefix = StorageKey::RoleMemberSet {
+            role,
+            scope_hash: env::sha256_array(&borsh::to_vec(&scope).unwrap()),
+        };
+        let inserted = self
+            .role_members
+            .entry(key)
+            .or_insert_with(|| IterableSet::new(prefix))
+            .insert(account_id.clone());
+
+        if inserted {
+            SurveyEvent::RoleGranted {
+                role,
+                account_id: account_id.clone(),
+                scope,
+            }
+            .emit();
+        }
+    }
+
+    fn remove_role(&mut self, role: Role, scope: RoleScope, account_id: &AccountId) {
+        let removed = self
+            .role_members
+            .get_mut(&(role, scope.clone()))
+            .is_some_and(|members| members.remove(account_id));
+
+        if removed {
+            SurveyEvent::RoleRevoked {
+                role,
+                account_id: account_id.clone(),
+                scope,
+            }
+            .emit();
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! NEP-145 storage management, shared by both contracts through `#[path]` like
+//! `fees.rs`.
+
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::{assert_one_yocto, env, near, AccountId, NearToken, Promise};
+
+use crate::{Quizzler, QuizzlerExt, SurveyEvent};
+
+const STORAGE_ACCOUNT_BYTES: u128 = 150; // storage of one storage_accounts entry
+
+/// NEP-145 balance of an account. `used` backs the storage of its surveys and
+/// its own registration, the rest can be withdrawn.
+#[near(serializers = [borsh])]
+#[derive(Clone)]
+pub struct StorageAccount {
+    total: NearToken,
+    used: NearToken,
+}
+
+impl StorageAccount {
+    fn available(&self) -> NearToken {
+        self.total.saturating_sub(self.used)
+    }
+
+    fn balance(&self) -> StorageBalance {
+        StorageBalance {
+            total: self.total,
+            available: self.available(),
+        }
+    }
+}
+
+#[near]
+impl StorageManagement for Quizzler {
+    /// Registers `account_id` (the caller by default) and adds the deposit to
+    /// its balance. With `registration_only` only the minimum is kept.
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let attached_deposit = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let min_balance = self.storage_balance_bounds().min;
+
+        let (mut account, deposit) = match self.storage_accounts.get(&account_id) {
+            Some(account) if registration_only == Some(true) => {
+                (account.clone(), NearToken::from_yoctonear(0))
+            }
+            Some(account) => (account.clone(), attached_deposit),
+            None => {
+                assert!(
+                    attached_deposit >= min_balance,
+                    "The attached deposit is less than the minimum storage balance"
+                );
+                let account = StorageAccount {
+                    total: NearToken::from_yoctonear(0),
+                    used: min_balance,
+                };
+                let deposit = if registration_only == Some(true) {
+                    min_balance
+                } else {
+                    attached_deposit
+                };
+                (account, deposit)
+            }
+        };
+
+        let available = account.available();
+        account.total = account.total.saturating_add(deposit);
+        self.escrow_balance = self
+            .escrow_balance
+            .saturating_add(account.available().saturating_sub(available));
+        self.storage_accounts.insert(account_id, account.clone());
+
+        Self::refund_surplus(attached_deposit, deposit);
+        account.balance()
+    }
+
+    /// Withdraws `amount` of the available balance, all of it by default.
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let account = self
+            .storage_accounts
+            .get_mut(&account_id)
+            .expect("The account is not registered");
+
+        let available = account.available();
+        let amount = amount.unwrap_or(available);
+        assert!(amount <= available, "Not enough available storage balance");
+        account.total = account.total.saturating_sub(amount);
+        let balance = account.balance();
+
+        if !amount.is_zero() {
+            self.escrow_balance = self.escrow_balance.saturating_sub(amount);
+            Promise::new(account_id.clone()).transfer(amount);
+
+            SurveyEvent::near_withdrawal(account_id, amount).emit();
+        }
+        balance
+    }
+
+    /// Closes the storage account and returns its balance. Accounts that still
+    /// pay for survey storage cannot unregister, `force` is not supported.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        assert!(force != Some(true), "Force unregistering is not supported");
+
+        let account_id = env::predecessor_account_id();
+        let Some(account) = self.storage_accounts.get(&account_id) else {
+            return false;
+        };
+        let min_balance = self.storage_balance_bounds().min;
+        assert!(
+            account.used <= min_balance,
+            "The account still pays for survey storage"
+        );
+
+        let amount = account.total;
+        self.escrow_balance = self.escrow_balance.saturating_sub(account.available());
+        self.storage_accounts.remove(&account_id);
+        Promise::new(account_id.clone()).transfer(amount);
+
+        SurveyEvent::near_withdrawal(account_id, amount).emit();
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: env::storage_byte_cost().saturating_mul(STORAGE_ACCOUNT_BYTES),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(StorageAccount::balance)
+    }
+}
+
+impl Quizzler {
+    /// Cost of the storage added since `initial_storage`. Collections only write
+    /// to storage when flushed, so flush the touched ones before calling it.
+    pub(crate) fn storage_cost_since(initial_storage: u64) -> NearToken {
+        env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage) as u128)
+    }
+
+    /// Cost of the storage released since `initial_storage`, flush the touched
+    /// collections first as for `storage_cost_since`.
+    pub(crate) fn storage_freed_since(initial_storage: u64) -> NearToken {
+        env::storage_byte_cost()
+            .saturating_mul(initial_storage.saturating_sub(env::storage_usage()) as u128)
+    }
+
+    /// Moves up to `amount` of the storage paid by `account_id` back to its
+    /// available balance, keeping the registration minimum. Returns the
+    /// released amount.
+    pub(crate) fn release_storage(
+        &mut self,
+        account_id: &AccountId,
+        amount: NearToken,
+    ) -> NearToken {
+        let min_balance = self.storage_balance_bounds().min;
+        let Some(account) = self.storage_accounts.get_mut(account_id) else {
+            return NearToken::from_yoctonear(0);
+        };
+
+        let released = amount.min(account.used.saturating_sub(min_balance));
+        account.used = account.used.saturating_sub(released);
+        self.escrow_balance = self.escrow_balance.saturating_add(released);
+        released
+    }
+
+    /// Charges `cost` to the storage balance of `account_id`, then to up to
+    /// `attached` of the current deposit, which also registers the account if
+    /// needed. Returns the charged amount and the part taken from `attached`;
+    /// what neither covers stays with the contract.
+    pub(crate) fn charge_storage(
+        &mut self,
+        account_id: &AccountId,
+        cost: NearToken,
+        attached: NearToken,
+    ) -> (NearToken, NearToken) {
+        let nothing = NearToken::from_yoctonear(0);
+        let min_balance = self.storage_balance_bounds().min;
+        let (mut account, registration) = match self.storage_accounts.get(account_id) {
+            Some(account) => (account.clone(), nothing),
+            None => (
+                StorageAccount {
+                    total: nothing,
+                    used: min_balance,
+                },
+                min_balance,
+            ),
+        };
+        if cost.is_zero() || registration > attached {
+            return (nothing, nothing);
+        }
+
+        let from_balance = cost.min(account.available());
+        let from_attached = cost
+            .saturating_sub(from_balance)
+            .min(attached.saturating_sub(registration));
+        account.total = account
+            .total
+            .saturating_add(registration)
+            .saturating_add(from_attached);
+        account.used = account
+            .used
+            .saturating_add(from_balance)
+            .saturating_add(from_attached);
+        self.escrow_balance = self.escrow_balance.saturating_sub(from_balance);
+        self.storage_accounts.insert(account_id.clone(), account);
+
+        (
+            from_balance.saturating_add(from_attached),
+            registration.saturating_add(from_attached),
+        )
+    }
+
+    /// Returns the part of the attached deposit above `required` to the caller.
+    pub(crate) fn refund_surplus(attached_deposit: NearToken, required: NearToken) {
+        let surplus = attached_deposit.saturating_sub(required);
+        if surplus.is_zero() {
+            return;
+        }
+
+        Promise::new(env::predecessor_account_id()).transfer(surplus);
+        SurveyEvent::near_withdrawal(env::predecessor_account_id(), surplus).emit();
+    }
+}